@@ -1,3 +1,4 @@
 cargo build --release && \
 cbindgen -c cbindgen.toml --output cpp/wtx_tools.h && \
+cbindgen -c cbindgen_c.toml --output cpp/wtx_tools_c.h && \
 clang++ cpp/test.cpp -o cpp/test.out -L ./target/release/ -I ./cpp/ -lwtx_tools
@@ -0,0 +1,110 @@
+/* Checks that wtx_tools_c.h works from plain C.
+ * Built and run by `cargo test --test c_api`. test.cpp covers every function, this only makes sure
+ * the C versions of the types, enums and flags can be used.
+ * Every generated texture is written to ./<name>.wtx, so the Rust side can decode and check it. */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include "wtx_tools_c.h"
+
+static int failures = 0;
+
+#define CHECK(cond)                                                     \
+    do {                                                                \
+        if (!(cond)) {                                                  \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+            failures++;                                                 \
+        }                                                               \
+    } while (0)
+
+static const unsigned char WTX_MAGIC[8] = { 0, 0, 0, 0, 0, 0, 0x12, 0 };
+
+/* checks the buffer looks like a wtx file, saves it as <name>.wtx and frees it */
+static void check_wtx(const char *name, TextureBuffer buf) {
+    char path[64];
+    FILE *out;
+
+    if (buf.data == NULL) {
+        fprintf(stderr, "%s: got an empty buffer\n", name);
+        failures++;
+        return;
+    }
+    CHECK(buf.len > WTX_HEADER_LEN);
+    CHECK(memcmp(buf.data, WTX_MAGIC, sizeof(WTX_MAGIC)) == 0);
+    printf("%s: %lu bytes\n", name, (unsigned long) buf.len);
+
+    snprintf(path, sizeof(path), "%s.wtx", name);
+    out = fopen(path, "wb");
+    CHECK(out != NULL);
+    if (out != NULL) {
+        fwrite(buf.data, 1, buf.len, out);
+        fclose(out);
+    }
+
+    free_texbuf(buf);
+}
+
+int main(void) {
+    float xs[] = { 0.1f, 0.2f, 0.3f, 0.4f, 0.5f, 0.6f, 0.7f };
+    float ys[] = { 0.5f, 0.2f, 0.2f, 0.4f, 0.3f, 0.6f, 0.5f };
+    SegmentKind segments[] = { SegmentKind_Line, SegmentKind_Quad, SegmentKind_Cubic };
+    LineStyle style = wtx_tools_default_line_style();
+    GrooveStyle groove = wtx_tools_default_groove_style();
+    WtxFlags flags = { WtxFlags_SRGB.bits | WtxFlags_SPECULAR.bits };
+    const char garbage[] = "not an image";
+    ImgFileBuffer image;
+    TextureBuffer empty;
+    const char *description = "{ \"type\": \"color_panel\", \"background\": \"dark_grey\", \"width\": 3, \"height\": 3, \"cells\": [] }";
+    size_t size;
+    uint8_t *buffer;
+    WtxToolsVersion version = wtx_tools_version();
+
+    printf("version: %u.%u.%u, abi %u\n", version.major, version.minor, version.patch, version.abi);
+    if (version.abi != WTX_TOOLS_ABI_VERSION) {
+        fprintf(stderr, "library has abi version %u, header has %u\n", version.abi, (unsigned) WTX_TOOLS_ABI_VERSION);
+        return 1;
+    }
+
+    /* the enums are fixed to 32 bits, so they match the Rust side whatever the C compiler picks for enums */
+    CHECK(sizeof(SegmentKind) == 4);
+    CHECK(sizeof(WtxFormat) == 4);
+    CHECK(SegmentKind_ArcCounterClockwise == 4);
+    CHECK(ColorPanelBackground_Elevator == 4);
+    CHECK(flags.bits == 5);
+
+    style.end_cap = LineEndCap_Nub;
+    check_wtx("c_desert_spec_path", generate_desert_spec_path(xs, ys, 7, segments, 3, 30.0f, 0, style));
+    check_wtx("c_desert_normal_path", generate_desert_normal_path(xs, ys, 7, segments, 3, 30.0f, 0, style, groove));
+    check_wtx("c_description", wtx_tools_render_description(description));
+
+    /* the caller owns this one */
+    size = wtx_tools_encoded_size(1024, 1024, true, WtxFormat_DXT5);
+    buffer = malloc(size);
+    CHECK(buffer != NULL && size > WTX_HEADER_LEN);
+    if (buffer != NULL) {
+        CHECK(wtx_tools_render_description_into(description, buffer, size) == size);
+        CHECK(memcmp(buffer, WTX_MAGIC, sizeof(WTX_MAGIC)) == 0);
+        free(buffer);
+    }
+
+    image.data = garbage;
+    image.len = sizeof(garbage);
+    empty = image_to_wtx(image, true, WtxFormat_DXT5, flags, WtxQuality_Fast);
+    CHECK(empty.data == NULL && empty.len == 0);
+    CHECK(wtx_tools_last_error() == WtxErrorCode_InvalidImage);
+    CHECK(wtx_tools_last_error_message() != NULL);
+    free_texbuf(empty);
+
+    /* enums are plain integers in C, so out of range values are errors instead of undefined behaviour */
+    style.end_cap = 7;
+    empty = generate_desert_spec_path(xs, ys, 7, segments, 3, 30.0f, 0, style);
+    CHECK(empty.data == NULL && empty.len == 0);
+    CHECK(wtx_tools_last_error() == WtxErrorCode_InvalidValue);
+
+    if (failures != 0) {
+        fprintf(stderr, "%d checks failed\n", failures);
+        return 1;
+    }
+    printf("all checks passed\n");
+    return 0;
+}
@@ -1,58 +1,217 @@
+// Calls every function exported by wtx_tools and checks the results.
+// Built and run by `cargo test --test c_api`, which also checks that wtx_tools.h is up to date.
+// Usage: test.out <image file>
+// Every generated texture is written to ./<name>.wtx, so the Rust side can decode and check it.
 #include <stdio.h>
+#include <string.h>
 #include "wtx_tools.h"
+#include <algorithm>
 #include <fstream>
+#include <iterator>
+#include <string>
 #include <vector>
 using namespace std;
 
-int main() {
-    
-    vector<float> xarray{ .1, .4, .4 };
-    vector<float> yarray{ .1, .1, .4 };
-    vector<float> xarray2{ .7, .5, .5 };
-    vector<float> yarray2{ .2, .2, .5 };
+static int failures = 0;
 
-   // TextureBuffer x = generate_desert_spec_line_sym(&xarray[0], &yarray[0], xarray.size(), 30.0,1);
-    TextureBuffer x = generate_desert_spec_line_2(&xarray[0], &yarray[0], xarray.size(), &xarray2[0], &yarray2[0], xarray2.size(), 30.0);
+#define CHECK(cond)                                                     \
+    do {                                                                \
+        if (!(cond)) {                                                  \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+            failures++;                                                 \
+        }                                                               \
+    } while (0)
 
-    WtxPuzzle3x3 puzzle = { {
-                WtxColor::NoColor,              WtxColor::TricolorGreen,    WtxColor::NoColor,
-                WtxColor::TricolorPurple,       WtxColor::NoColor,          WtxColor::TricolorGreen,
-                WtxColor::TricolorWhite,        WtxColor::TricolorPurple,   WtxColor::NoColor} };
+static const unsigned char WTX_MAGIC[8] = { 0, 0, 0, 0, 0, 0, 0x12, 0 };
 
-    // TextureBuffer x = generate_tricolor_panel_3x3_wtx(puzzle, ColorPanelBackground::Blueprint);
+// checks the buffer looks like a wtx file, saves it as <name>.wtx and frees it
+static void check_wtx(const char *name, TextureBuffer buf) {
+    if (buf.data == nullptr) {
+        fprintf(stderr, "%s: got an empty buffer\n", name);
+        failures++;
+        return;
+    }
+    CHECK(buf.len > WTX_HEADER_LEN);
+    CHECK(memcmp(buf.data, WTX_MAGIC, sizeof(WTX_MAGIC)) == 0);
+    printf("%s: %zu bytes\n", name, buf.len);
 
+    ofstream out(string(name) + ".wtx", ios::out | ios::binary);
+    out.write((const char *) buf.data, buf.len);
+    out.close();
 
-    
-    // TextureBuffer x = generate_desert_spec_wtx("Center Top TopLeft BottomLeft Bottom BottomRight TopRight TopRightEnd");
-    
-    printf("got %lu bytes wtx file from rust.\n", x.len);
+    free_texbuf(buf);
+}
 
-    ofstream outputBuffer("./color_panel_custom.wtx", ios::out | ios::binary);
-    outputBuffer.write((const char *) x.data, x.len);
-    // outputBuffer.
-    outputBuffer.close();
+// checks that an _into function wrote `size` bytes, the same as the allocating version saved to <name>.wtx
+static void check_into(const char *name, size_t size, const vector<uint8_t> &out) {
+    ifstream file(string(name) + ".wtx", ios::binary);
+    vector<uint8_t> expected((istreambuf_iterator<char>(file)), istreambuf_iterator<char>());
+    if (size != expected.size() || !equal(expected.begin(), expected.end(), out.begin())) {
+        fprintf(stderr, "%s: _into wrote %zu bytes that don't match the %zu from the allocating version\n", name, size, expected.size());
+        failures++;
+    }
+}
 
+// checks that invalid input gave the documented empty buffer
+static void check_empty(const char *name, TextureBuffer buf) {
+    if (buf.data != nullptr || buf.len != 0) {
+        fprintf(stderr, "%s: expected an empty buffer, got %zu bytes\n", name, buf.len);
+        failures++;
+        free_texbuf(buf);
+    }
+}
 
-    free_texbuf(x); //rust lib has to be the one to call free() on that memory?
+int main(int argc, char **argv) {
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s <image file>\n", argv[0]);
+        return 2;
+    }
 
+    // checked first, a mismatched library can't be trusted with anything else
+    WtxToolsVersion version = wtx_tools_version();
+    printf("version: %u.%u.%u, abi %u\n", version.major, version.minor, version.patch, version.abi);
+    if (version.abi != WTX_TOOLS_ABI_VERSION) {
+        fprintf(stderr, "library has abi version %u, header has %u\n", version.abi, WTX_TOOLS_ABI_VERSION);
+        return 1;
+    }
 
-    ifstream fileBuffer("./Cursor_1_Happy_right.png", ios::ate | ios::binary);
+    // color panels. the grid is a 3x3 puzzle, so 7x7 and column-major, with the stones on odd cells
+    vector<uint32_t> grid(7 * 7, 0);
+    const uint32_t stones[9] = { 2, 0, 4, 0, 5, 7, 8, 0, 9 };
+    for (int i = 0; i < 9; i++) {
+        int x = (i % 3) * 2 + 1;
+        int y = (i / 3) * 2 + 1;
+        if (stones[i] != 0) {
+            grid[y + x * 7] = 0x100 | stones[i];
+        }
+    }
+    check_wtx("colorpanel", wtx_tools_generate_colorpanel_from_grid(grid.data(), 7, 7, (uint32_t) ColorPanelBackground::Blueprint));
+    check_wtx("colorpanel_saved", wtx_tools_generate_colorpanel_from_grid_and_save(grid.data(), 7, 7, (uint32_t) ColorPanelBackground::Elevator, 0x41));
+    ifstream saved("./generated_41.png");
+    CHECK(saved.good());
+    check_empty("colorpanel_null", wtx_tools_generate_colorpanel_from_grid(nullptr, 7, 7, (uint32_t) ColorPanelBackground::White));
+    CHECK(wtx_tools_last_error() == WtxErrorCode::NullPointer);
+    CHECK(wtx_tools_last_error_message() != nullptr && strstr(wtx_tools_last_error_message(), "grid is null") != nullptr);
+    vector<uint32_t> bad_grid = grid;
+    bad_grid[1 + 1 * 7] = 0x100 | 0xF;
+    check_empty("colorpanel_bad_color", wtx_tools_generate_colorpanel_from_grid(bad_grid.data(), 7, 7, (uint32_t) ColorPanelBackground::White));
+    CHECK(wtx_tools_last_error() == WtxErrorCode::UnknownStoneColor);
+    check_empty("colorpanel_bad_background", wtx_tools_generate_colorpanel_from_grid(grid.data(), 7, 7, 5));
+    CHECK(wtx_tools_last_error() == WtxErrorCode::InvalidValue);
+    // a successful call clears it
+    check_wtx("colorpanel_again", wtx_tools_generate_colorpanel_from_grid(grid.data(), 7, 7, (uint32_t) ColorPanelBackground::White));
+    CHECK(wtx_tools_last_error() == WtxErrorCode::None);
+    CHECK(wtx_tools_last_error_message() == nullptr);
 
-    if (fileBuffer.is_open()) {
-        std::streamsize length = fileBuffer.tellg();
-        printf("opened image file, it has %ld length\n", length);
-        fileBuffer.seekg(0, std::ios::beg);
-        char * buffer = new char [length];
-        fileBuffer.read (buffer,length);
-        ImgFileBuffer inputfile;
-        inputfile.data = buffer;
-        inputfile.len = length;
+    // desert spec and normal maps
+    vector<float> xs{ .1, .4, .4 };
+    vector<float> ys{ .1, .1, .4 };
+    vector<float> xs2{ .7, .5, .5 };
+    vector<float> ys2{ .2, .2, .5 };
+    check_wtx("desert_spec_line", generate_desert_spec_line(xs.data(), ys.data(), xs.size(), 30.0));
+    check_wtx("desert_spec_line_2", generate_desert_spec_line_2(xs.data(), ys.data(), xs.size(), xs2.data(), ys2.data(), xs2.size(), 30.0));
+    check_wtx("desert_spec_line_sym", generate_desert_spec_line_sym(xs.data(), ys.data(), xs.size(), 30.0, 1));
 
-        TextureBuffer y = image_to_wtx(inputfile, true, WtxFormat::DXT1, 0x05);
-        printf("got %lu bytes wtx file from rust.\n", y.len);
-        free_texbuf(y); //rust lib has to be the one to call free() on that memory?
+    LineStyle style = wtx_tools_default_line_style();
+    CHECK(style.opacity > 0.0f && style.opacity <= 1.0f);
+    style.r = style.g = style.b = 40;
+    style.end_cap = (uint32_t) LineEndCap::Nub;
+    check_wtx("desert_spec_line_styled", generate_desert_spec_line_styled(xs.data(), ys.data(), xs.size(), 24.0, 2, style));
 
-    }   
-    
-}
+    // a line, a quad and a cubic curve: 1 + 1 + 2 + 3 points
+    vector<float> path_xs{ .1, .2, .3, .4, .5, .6, .7 };
+    vector<float> path_ys{ .5, .2, .2, .4, .3, .6, .5 };
+    vector<uint32_t> segments{ (uint32_t) SegmentKind::Line, (uint32_t) SegmentKind::Quad, (uint32_t) SegmentKind::Cubic };
+    check_wtx("desert_spec_path", generate_desert_spec_path(path_xs.data(), path_ys.data(), path_xs.size(), segments.data(), segments.size(), 30.0, 0, style));
+
+    GrooveStyle groove = wtx_tools_default_groove_style();
+    CHECK(groove.depth != 0.0f);
+    check_wtx("desert_normal_line_sym", generate_desert_normal_line_sym(xs.data(), ys.data(), xs.size(), 30.0, 1, groove));
+    check_wtx("desert_normal_path", generate_desert_normal_path(path_xs.data(), path_ys.data(), path_xs.size(), segments.data(), segments.size(), 30.0, 0, style, groove));
+
+    check_empty("desert_spec_bad_symmetry", generate_desert_spec_line_sym(xs.data(), ys.data(), xs.size(), 30.0, 99));
+    CHECK(wtx_tools_last_error() == WtxErrorCode::UnknownSymmetry);
+    check_empty("desert_spec_too_thick", generate_desert_spec_line_styled(xs.data(), ys.data(), xs.size(), 10000.0, 0, style));
+    check_empty("desert_spec_missing_points", generate_desert_spec_path(path_xs.data(), path_ys.data(), 3, segments.data(), segments.size(), 30.0, 0, style));
+    // enum values from C are checked, not trusted
+    vector<uint32_t> bad_segments{ (uint32_t) SegmentKind::Line, 5 };
+    check_empty("desert_spec_bad_segment", generate_desert_spec_path(path_xs.data(), path_ys.data(), path_xs.size(), bad_segments.data(), bad_segments.size(), 30.0, 0, style));
+    CHECK(wtx_tools_last_error() == WtxErrorCode::InvalidValue);
+    LineStyle bad_style = style;
+    bad_style.end_cap = 2;
+    check_empty("desert_spec_bad_end_cap", generate_desert_spec_line_styled(xs.data(), ys.data(), xs.size(), 24.0, 2, bad_style));
+    CHECK(wtx_tools_last_error() == WtxErrorCode::InvalidValue);
+
+    // image conversion
+    ifstream file(argv[1], ios::binary);
+    if (!file.is_open()) {
+        fprintf(stderr, "could not open %s\n", argv[1]);
+        return 2;
+    }
+    vector<char> contents((istreambuf_iterator<char>(file)), istreambuf_iterator<char>());
+    ImgFileBuffer image = { contents.data(), contents.size() };
+    check_wtx("image", image_to_wtx(image, true, (uint32_t) WtxFormat::DXT1, WtxFlags_SRGB | WtxFlags_SPECULAR, (uint32_t) WtxQuality::Fast));
+    check_wtx("image_auto", image_to_wtx(image, false, (uint32_t) WtxFormat::Auto, WtxFlags{ 0 }, (uint32_t) WtxQuality::Normal));
 
+    const char garbage[] = "not an image";
+    ImgFileBuffer bad_image = { garbage, sizeof(garbage) };
+    check_empty("image_garbage", image_to_wtx(bad_image, true, (uint32_t) WtxFormat::DXT5, WtxFlags_SRGB, (uint32_t) WtxQuality::Fast));
+    check_empty("image_bad_flags", image_to_wtx(image, true, (uint32_t) WtxFormat::DXT5, WtxFlags{ 0x80 }, (uint32_t) WtxQuality::Fast));
+    check_empty("image_bad_format", image_to_wtx(image, true, 3, WtxFlags{ 0 }, (uint32_t) WtxQuality::Fast));
+    CHECK(wtx_tools_last_error() == WtxErrorCode::InvalidValue);
+    check_empty("image_bad_quality", image_to_wtx(image, true, (uint32_t) WtxFormat::DXT5, WtxFlags{ 0 }, 0xffffffff));
+    CHECK(wtx_tools_last_error() == WtxErrorCode::InvalidValue);
+    // the message has the value exactly as it was passed
+    CHECK(strstr(wtx_tools_last_error_message(), "4294967295") != nullptr);
+
+    // descriptions
+    check_wtx("description", wtx_tools_render_description(
+        "{ \"type\": \"color_panel\", \"background\": \"white\", \"width\": 3, \"height\": 3,"
+        "  \"cells\": [{ \"x\": 1, \"y\": 0, \"decoration\": \"stone\", \"color\": \"tricolor_new_pink\" }] }"));
+    check_empty("description_invalid", wtx_tools_render_description("{ \"type\": \"nothing\" }"));
+    check_empty("description_null", wtx_tools_render_description(nullptr));
+    check_empty("description_panel_size", wtx_tools_render_description(
+        "{ \"type\": \"color_panel\", \"background\": \"white\", \"width\": 4, \"height\": 5, \"cells\": [] }"));
+    CHECK(wtx_tools_last_error() == WtxErrorCode::UnsupportedPanelSize);
+
+    // caller buffers. the _into functions write the same bytes the allocating ones return
+    size_t panel_size = wtx_tools_encoded_size(1024, 1024, true, (uint32_t) WtxFormat::DXT5);
+    size_t desert_size = wtx_tools_encoded_size(512, 512, true, (uint32_t) WtxFormat::DXT1);
+    CHECK(panel_size > desert_size);
+    CHECK(wtx_tools_encoded_size(0, 512, true, (uint32_t) WtxFormat::DXT1) == 0);
+    CHECK(wtx_tools_encoded_size(512, 512, true, 7) == 0);
+    CHECK(wtx_tools_generate_colorpanel_from_grid_into(grid.data(), 7, 7, (uint32_t) ColorPanelBackground::Blueprint, nullptr, 0) == panel_size);
+
+    vector<uint8_t> out(panel_size, 0xab);
+    // too small, only the size comes back
+    CHECK(wtx_tools_generate_colorpanel_from_grid_into(grid.data(), 7, 7, (uint32_t) ColorPanelBackground::Blueprint, out.data(), panel_size - 1) == panel_size);
+    CHECK(all_of(out.begin(), out.end(), [](uint8_t b) { return b == 0xab; }));
+    size_t size = wtx_tools_generate_colorpanel_from_grid_into(grid.data(), 7, 7, (uint32_t) ColorPanelBackground::Blueprint, out.data(), out.size());
+    check_into("colorpanel", size, out);
+    size = wtx_tools_render_description_into(
+        "{ \"type\": \"color_panel\", \"background\": \"white\", \"width\": 3, \"height\": 3,"
+        "  \"cells\": [{ \"x\": 1, \"y\": 0, \"decoration\": \"stone\", \"color\": \"tricolor_new_pink\" }] }", out.data(), out.size());
+    check_into("description", size, out);
+    size = generate_desert_spec_path_into(path_xs.data(), path_ys.data(), path_xs.size(), segments.data(), segments.size(), 30.0, 0, style, out.data(), desert_size);
+    check_into("desert_spec_path", size, out);
+    size = generate_desert_normal_path_into(path_xs.data(), path_ys.data(), path_xs.size(), segments.data(), segments.size(), 30.0, 0, style, groove, out.data(), desert_size);
+    check_into("desert_normal_path", size, out);
+    // auto gives the DXT5 size, which is enough either way
+    size = image_to_wtx_into(image, false, (uint32_t) WtxFormat::Auto, WtxFlags{ 0 }, (uint32_t) WtxQuality::Normal, out.data(), out.size());
+    CHECK(size <= wtx_tools_encoded_size(32, 32, false, (uint32_t) WtxFormat::Auto));
+    check_into("image_auto", size, out);
+
+    CHECK(wtx_tools_render_description_into("{ \"type\": \"nothing\" }", out.data(), out.size()) == 0);
+    CHECK(wtx_tools_last_error() == WtxErrorCode::InvalidDescription);
+    CHECK(image_to_wtx_into(bad_image, true, (uint32_t) WtxFormat::DXT5, WtxFlags_SRGB, (uint32_t) WtxQuality::Fast, out.data(), out.size()) == 0);
+
+    // freeing an empty buffer does nothing
+    free_texbuf(TextureBuffer{ nullptr, 0 });
+
+    if (failures != 0) {
+        fprintf(stderr, "%d checks failed\n", failures);
+        return 1;
+    }
+    printf("all checks passed\n");
+    return 0;
+}
@@ -0,0 +1,411 @@
+#ifndef WTX_TOOLS_C_H
+#define WTX_TOOLS_C_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+
+// Size of the header at the start of every wtx file. The texture data starts right after it.
+#define WTX_HEADER_LEN 44
+
+// Version of the C interface. Bumped whenever an exported function or type changes in a way that
+// breaks code built against an older header, e.g. a function is renamed or removed or a struct changes layout.
+// Hosts should compare it to `wtx_tools_version().abi` when loading the library.
+#define WTX_TOOLS_ABI_VERSION 1
+
+// Enum used to decide which background to give a generated color-panel image
+enum ColorPanelBackground
+#ifdef __cplusplus
+  : uint32_t
+#endif // __cplusplus
+ {
+  // used on introductory puzzles
+  ColorPanelBackground_Blueprint,
+  // used in the 2 shipping container puzzles
+  ColorPanelBackground_White,
+  // First 2 color-filter puzzles
+  ColorPanelBackground_LightGrey,
+  // third color-filter puzzle. Slightly darker gray
+  ColorPanelBackground_DarkGrey,
+  // only used on elevator
+  ColorPanelBackground_Elevator,
+};
+#ifndef __cplusplus
+typedef uint32_t ColorPanelBackground;
+#endif // __cplusplus
+
+// How the far end of a line on a desert spec map is drawn
+enum LineEndCap
+#ifdef __cplusplus
+  : uint32_t
+#endif // __cplusplus
+ {
+  // rounded end, like the rest of the line
+  LineEndCap_Round,
+  // flat end with a thinner rounded stub past it, like the exit nub on a panel
+  LineEndCap_Nub,
+};
+#ifndef __cplusplus
+typedef uint32_t LineEndCap;
+#endif // __cplusplus
+
+// Kind of each segment in a curved desert spec map line.
+// Segments are drawn one after another, each starting where the last one ended,
+// and each uses up the listed number of points from the point arrays.
+enum SegmentKind
+#ifdef __cplusplus
+  : uint32_t
+#endif // __cplusplus
+ {
+  // straight line. 1 point: the end
+  SegmentKind_Line,
+  // quadratic curve. 2 points: the control point, then the end
+  SegmentKind_Quad,
+  // cubic curve. 3 points: both control points, then the end
+  SegmentKind_Cubic,
+  // clockwise arc. 2 points: the centre, then a point at the angle the arc should stop at.
+  // The radius is the distance from the current position to the centre.
+  // If the arc would stop where it started, a full circle is drawn.
+  SegmentKind_ArcClockwise,
+  // same as `ArcClockwise`, going the other way around
+  SegmentKind_ArcCounterClockwise,
+};
+#ifndef __cplusplus
+typedef uint32_t SegmentKind;
+#endif // __cplusplus
+
+// Numeric version of `Error`, for C, where `wtx_tools_last_error()` returns it.
+// The values never change, new errors get new numbers at the end.
+enum WtxErrorCode
+#ifdef __cplusplus
+  : uint32_t
+#endif // __cplusplus
+ {
+  // the last call succeeded
+  WtxErrorCode_None = 0,
+  WtxErrorCode_TooLarge = 1,
+  WtxErrorCode_EmptyImage = 2,
+  WtxErrorCode_UnknownFlags = 3,
+  WtxErrorCode_InvalidHeader = 4,
+  WtxErrorCode_InvalidImage = 5,
+  WtxErrorCode_InvalidDescription = 6,
+  WtxErrorCode_InvalidGrid = 7,
+  WtxErrorCode_UnknownStoneColor = 8,
+  WtxErrorCode_UnsupportedStoneCount = 9,
+  WtxErrorCode_CellOutsideGrid = 10,
+  WtxErrorCode_NotEnoughPoints = 11,
+  WtxErrorCode_UnknownSymmetry = 12,
+  // also used for enum values that don't exist
+  WtxErrorCode_InvalidValue = 13,
+  WtxErrorCode_NullPointer = 14,
+  WtxErrorCode_EncodeFailed = 15,
+  WtxErrorCode_DecodeFailed = 16,
+  WtxErrorCode_Io = 17,
+  WtxErrorCode_InvalidManifest = 18,
+  WtxErrorCode_InvalidPackage = 19,
+  WtxErrorCode_EntryNotFound = 20,
+  WtxErrorCode_UnsupportedPanelSize = 21,
+};
+#ifndef __cplusplus
+typedef uint32_t WtxErrorCode;
+#endif // __cplusplus
+
+enum WtxFormat
+#ifdef __cplusplus
+  : uint32_t
+#endif // __cplusplus
+ {
+  WtxFormat_DXT5,
+  WtxFormat_DXT1,
+  // pick DXT1 or DXT5 based on the image's alpha channel. see `WtxFormat::for_image`
+  WtxFormat_Auto,
+};
+#ifndef __cplusplus
+typedef uint32_t WtxFormat;
+#endif // __cplusplus
+
+// How much effort the block compressor puts into encoding.
+// `Fast` is fine for textures generated at runtime, slower settings give fewer artifacts on hand-made textures.
+enum WtxQuality
+#ifdef __cplusplus
+  : uint32_t
+#endif // __cplusplus
+ {
+  // the blocks of image_dds' compressor as they are
+  WtxQuality_Fast,
+  // refits the endpoints of every block to its pixels
+  WtxQuality_Normal,
+  // also searches around the endpoints. a lot slower than `Normal`
+  WtxQuality_Slow,
+};
+#ifndef __cplusplus
+typedef uint32_t WtxQuality;
+#endif // __cplusplus
+
+// C-and-Rust readable struct. Contains wtx-formatted texture.
+typedef struct TextureBuffer {
+  uint8_t *data;
+  size_t len;
+} TextureBuffer;
+
+// Shape of the groove carved into a generated desert normal map.
+// `GrooveStyle::default()` (or `wtx_tools_default_groove_style()` from C) is a reasonable starting point.
+typedef struct GrooveStyle {
+  // how deep the groove is, in pixels. deeper grooves have steeper walls
+  float depth;
+  // how soft the edge of the groove is, as a blur sigma in pixels. 0 gives a hard vertical wall
+  float bevel;
+} GrooveStyle;
+
+// Styling of the line drawn on a desert spec map, the C version of `desert::LineStyle`.
+// `wtx_tools_default_line_style()` is the plain black line the other desert functions draw.
+typedef struct LineStyle {
+  // line color. spec maps are greyscale, so usually r == g == b, with black being no reflection
+  uint8_t r;
+  uint8_t g;
+  uint8_t b;
+  // 0.0 - 1.0. lower values let more of the background specular through the line
+  float opacity;
+  // sigma of the gaussian blur applied to the line. 0 disables blurring
+  float blur;
+  // radius of the circle at the start of the line, as a multiple of the line thickness. 0 disables the circle
+  float start_radius;
+  // a `LineEndCap` value. anything else is an `InvalidValue` error
+  uint32_t end_cap;
+} LineStyle;
+
+// C-and-Rust readable struct. Contains an image, png/jpeg/etc, to be converted to a wtx texture.
+// image can be any format readable by rust's `image` crate.
+typedef struct ImgFileBuffer {
+  const char *data;
+  size_t len;
+} ImgFileBuffer;
+
+// Flags stored in header byte 20 of a wtx file.
+// Nothing documents these, so the names come from which of the game's textures have them set.
+// Mipmaps don't have a flag, their count has its own header field.
+typedef struct WtxFlags {
+  uint8_t bits;
+} WtxFlags;
+// set on color textures (the color bunker panels, desert spec maps) and not on normal maps,
+// so most likely the texture is sampled as sRGB
+#define WtxFlags_SRGB (WtxFlags){ .bits = (uint8_t)1 }
+// set on the desert panels' specular maps
+#define WtxFlags_SPECULAR (WtxFlags){ .bits = (uint8_t)4 }
+
+// Version of a loaded wtx_tools library, returned by `wtx_tools_version()`.
+typedef struct WtxToolsVersion {
+  // crate version, major.minor.patch
+  uint32_t major;
+  uint32_t minor;
+  uint32_t patch;
+  // `WTX_TOOLS_ABI_VERSION` of the library
+  uint32_t abi;
+} WtxToolsVersion;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Call this to free a rust-allocated TextureBuffer
+// Rust will keep track of memory it allocated and must be informed to free it.
+void free_texbuf(struct TextureBuffer buf);
+
+//Generates a normal map to go with a spec map from `generate_desert_spec_line_sym`, using the same points,
+//thickness and symmetry. The line is carved into the panel as a groove shaped by `groove`.
+//generated images are 512x512 squares.
+struct TextureBuffer generate_desert_normal_line_sym(const float *xpoints,
+                                                     const float *ypoints,
+                                                     size_t numpoints,
+                                                     float thickness,
+                                                     int32_t symmetry,
+                                                     struct GrooveStyle groove);
+
+//Generates a normal map to go with a spec map from `generate_desert_spec_path`, taking the same arguments
+//plus the shape of the groove.
+struct TextureBuffer generate_desert_normal_path(const float *xpoints,
+                                                 const float *ypoints,
+                                                 size_t numpoints,
+                                                 const uint32_t *segments,
+                                                 size_t numsegments,
+                                                 float thickness,
+                                                 int32_t symmetry,
+                                                 struct LineStyle style,
+                                                 struct GrooveStyle groove);
+
+//Same as `generate_desert_normal_path`, but writes the texture into `out` instead of allocating it.
+//Returns the size of the texture, which is only written if it fits in `out_capacity` bytes, so a null `out` just gets the size.
+//`wtx_tools_encoded_size` gives the size up front. If the normal map can't be generated, the error is kept for `wtx_tools_last_error()` and 0 is returned.
+size_t generate_desert_normal_path_into(const float *xpoints,
+                                        const float *ypoints,
+                                        size_t numpoints,
+                                        const uint32_t *segments,
+                                        size_t numsegments,
+                                        float thickness,
+                                        int32_t symmetry,
+                                        struct LineStyle style,
+                                        struct GrooveStyle groove,
+                                        uint8_t *out,
+                                        size_t out_capacity);
+
+//Generates an arbitrary spec map with a line pattern according to an array of x/y points.
+//generated images are 512x512 squares
+struct TextureBuffer generate_desert_spec_line(const float *xpoints,
+                                               const float *ypoints,
+                                               size_t numpoints,
+                                               float thickness);
+
+struct TextureBuffer generate_desert_spec_line_2(const float *xpoints,
+                                                 const float *ypoints,
+                                                 size_t numpoints,
+                                                 const float *xpoints2,
+                                                 const float *ypoints2,
+                                                 size_t numpoints2,
+                                                 float thickness);
+
+//Same as `generate_desert_spec_line_sym`, but the line is drawn with `style` instead of the default black line.
+//Use `wtx_tools_default_line_style()` to get a style to start from.
+//If the points, symmetry or `style.end_cap` are invalid, the error is kept for `wtx_tools_last_error()` and an empty TextureBuffer (null `data`, 0 `len`) is returned.
+struct TextureBuffer generate_desert_spec_line_styled(const float *xpoints,
+                                                      const float *ypoints,
+                                                      size_t numpoints,
+                                                      float thickness,
+                                                      int32_t symmetry,
+                                                      struct LineStyle style);
+
+//Generates an arbitrary spec map with a line pattern according to an array of x/y points with symmetry.
+//generated images are 512x512 squares.
+//symmetry is an `int` corresponding to the randomizers' existing Symmetry enum.
+struct TextureBuffer generate_desert_spec_line_sym(const float *xpoints,
+                                                   const float *ypoints,
+                                                   size_t numpoints,
+                                                   float thickness,
+                                                   int32_t symmetry);
+
+//Generates a spec map like `generate_desert_spec_line_styled`, but the line can contain curves.
+//`segments` lists what kind of segment to draw next as `SegmentKind` values, and each one takes its points
+//in order from the x/y arrays. The first point is where the line starts, so a straight line
+//needs `numsegments + 1` points.
+struct TextureBuffer generate_desert_spec_path(const float *xpoints,
+                                               const float *ypoints,
+                                               size_t numpoints,
+                                               const uint32_t *segments,
+                                               size_t numsegments,
+                                               float thickness,
+                                               int32_t symmetry,
+                                               struct LineStyle style);
+
+//Same as `generate_desert_spec_path`, but writes the texture into `out` instead of allocating it.
+//The other spec map functions draw straight lines, which this does with `SegmentKind::Line` segments.
+//Returns the size of the texture, which is only written if it fits in `out_capacity` bytes, so a null `out` just gets the size.
+//`wtx_tools_encoded_size` gives the size up front. If the spec map can't be generated, the error is kept for `wtx_tools_last_error()` and 0 is returned.
+size_t generate_desert_spec_path_into(const float *xpoints,
+                                      const float *ypoints,
+                                      size_t numpoints,
+                                      const uint32_t *segments,
+                                      size_t numsegments,
+                                      float thickness,
+                                      int32_t symmetry,
+                                      struct LineStyle style,
+                                      uint8_t *out,
+                                      size_t out_capacity);
+
+// Converts ImgFileBuffer to a TextureBuffer containing an wtx-formatted image
+// `format` and `quality` are `WtxFormat` and `WtxQuality` values.
+// If the image can't be read, `format` or `quality` is out of range, is too large for a wtx or `flags` has unknown bits set, the error is kept for `wtx_tools_last_error()` and an empty TextureBuffer (null `data`, 0 `len`) is returned.
+struct TextureBuffer image_to_wtx(struct ImgFileBuffer image,
+                                  bool gen_mipmaps,
+                                  uint32_t format,
+                                  struct WtxFlags flags,
+                                  uint32_t quality);
+
+// Same as `image_to_wtx`, but writes the texture into `out` instead of allocating it.
+// Returns the size of the texture, which is only written if it fits in `out_capacity` bytes, so a null `out` just gets the size.
+// `wtx_tools_encoded_size` gives the size up front. If the image can't be converted, the error is kept for `wtx_tools_last_error()` and 0 is returned.
+size_t image_to_wtx_into(struct ImgFileBuffer image,
+                         bool gen_mipmaps,
+                         uint32_t format,
+                         struct WtxFlags flags,
+                         uint32_t quality,
+                         uint8_t *out,
+                         size_t out_capacity);
+
+// Returns a default groove shape for the desert normal map functions.
+struct GrooveStyle wtx_tools_default_groove_style(void);
+
+// Returns the line style used by the desert functions that don't take a `LineStyle`.
+struct LineStyle wtx_tools_default_line_style(void);
+
+// Returns the size in bytes of a wtx file `width` x `height` pixels big, as the `_into` functions write it.
+// For `WtxFormat::Auto` this is the DXT5 size, which is enough for either format.
+// The color panels are 1024x1024 DXT5 and the desert maps 512x512 DXT1, both with mipmaps.
+// `format` is a `WtxFormat` value. Returns 0 if it isn't one, or the size can't be stored in a wtx file.
+size_t wtx_tools_encoded_size(uint32_t width,
+                              uint32_t height,
+                              bool gen_mipmaps,
+                              uint32_t format);
+
+// Generates a complete 'wtx' file from a `_grid`, with background `bg`, a `ColorPanelBackground` value
+// The `*cost u32` in the arguments should pbe the start of a structure equivalent to  `_grid` from a `Panel`
+// It should be flattened to a contiguous array first, so that this rust code can read it.
+// Rust recalculates the size through the width and height. Width and height here is of the grid array - not
+// what you would probably consider the size of the puzzle. For a 3x3 puzzle for instance, thats (3*2 +1) in each dimension on the array, so 7x7.
+// If the grid can't be read, the error is kept for `wtx_tools_last_error()` and an empty TextureBuffer (null `data`, 0 `len`) is returned.
+struct TextureBuffer wtx_tools_generate_colorpanel_from_grid(const uint32_t *grid,
+                                                             size_t width,
+                                                             size_t height,
+                                                             uint32_t bg);
+
+// This function is intended to be called by witness randomizer code
+// It is the same as `wtx_tools_generate_colorpanel_from_grid` but with an extra `id` argument.
+// this will save the generated image to disk as ./generated_{id}.png, with `id` written in hex
+struct TextureBuffer wtx_tools_generate_colorpanel_from_grid_and_save(const uint32_t *grid,
+                                                                      size_t width,
+                                                                      size_t height,
+                                                                      uint32_t bg,
+                                                                      int32_t id);
+
+// Same as `wtx_tools_generate_colorpanel_from_grid`, but writes the texture into `out` instead of allocating it.
+// Returns the size of the texture, which is only written if it fits in `out_capacity` bytes, so a null `out` just gets the size.
+// `wtx_tools_encoded_size` gives the size up front. If the grid can't be read, the error is kept for `wtx_tools_last_error()` and 0 is returned.
+size_t wtx_tools_generate_colorpanel_from_grid_into(const uint32_t *grid,
+                                                    size_t width,
+                                                    size_t height,
+                                                    uint32_t bg,
+                                                    uint8_t *out,
+                                                    size_t out_capacity);
+
+// Returns why the last function on this thread that generates a texture failed, or `WtxErrorCode::None` if it succeeded.
+// That's every function returning a `TextureBuffer`, and the `_into` ones.
+WtxErrorCode wtx_tools_last_error(void);
+
+// Returns the message for `wtx_tools_last_error()`, or null if there was no error.
+// The string belongs to the library, and stays valid until the next texture generating call on this thread.
+const char *wtx_tools_last_error_message(void);
+
+// Generates a texture from a JSON panel description. See `src/description.rs` for the format.
+// `json` must be a null-terminated UTF-8 string.
+// If the description can't be read or rendered, the error is kept for `wtx_tools_last_error()` and an empty TextureBuffer (null `data`, 0 `len`) is returned.
+struct TextureBuffer wtx_tools_render_description(const char *json);
+
+// Same as `wtx_tools_render_description`, but writes the texture into `out` instead of allocating it.
+// Returns the size of the texture, which is only written if it fits in `out_capacity` bytes, so a null `out` just gets the size.
+// `wtx_tools_encoded_size` gives the size up front. If the description can't be read or rendered, the error is kept for `wtx_tools_last_error()` and 0 is returned.
+size_t wtx_tools_render_description_into(const char *json,
+                                         uint8_t *out,
+                                         size_t out_capacity);
+
+// Returns the version of the loaded library.
+// If `abi` isn't the `WTX_TOOLS_ABI_VERSION` from the header you built against, the library doesn't match the header
+// and none of its other functions should be called.
+// This function and `WtxToolsVersion` never change, so this check is always safe to make.
+struct WtxToolsVersion wtx_tools_version(void);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* WTX_TOOLS_C_H */
@@ -0,0 +1,37 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wtx_tools::WtxHeader;
+
+/// a wtx header with every field up to the fuzzer, so most inputs get past the magic bytes and format
+#[derive(Debug, arbitrary::Arbitrary)]
+struct Input {
+    length: u32,
+    width: u16,
+    height: u16,
+    depth: u16,
+    mipmaps: u16,
+    flags: u8,
+    dxt5: bool,
+    data: Vec<u8>,
+}
+
+fuzz_target!(|input: Input| {
+    let mut wtx = vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x12, 0x00];
+    wtx.extend_from_slice(&input.length.to_le_bytes());
+    wtx.extend_from_slice(&input.width.to_le_bytes());
+    wtx.extend_from_slice(&input.height.to_le_bytes());
+    wtx.extend_from_slice(&input.depth.to_le_bytes());
+    wtx.extend_from_slice(&input.mipmaps.to_le_bytes());
+    wtx.extend_from_slice(&[input.flags, 0, 0, 0]);
+    wtx.extend_from_slice(&[0; 16]);
+    wtx.extend_from_slice(if input.dxt5 { b"DXT5" } else { b"DXT1" });
+    wtx.extend_from_slice(&input.data);
+
+    if let Ok(img) = wtx_tools::decode_wtx(&wtx) {
+        let header = WtxHeader::parse(&wtx).unwrap();
+        assert_eq!(img.dimensions(), (header.width as u32, header.height as u32));
+    }
+    // and the same for anything at all after the magic bytes
+    let _ = wtx_tools::decode_wtx(&[&wtx[..8], &input.data].concat());
+});
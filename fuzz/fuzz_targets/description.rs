@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wtx_tools::description::PanelDescription;
+
+fuzz_target!(|json: &str| {
+    if let Ok(description) = PanelDescription::from_json(json) {
+        let _ = description.render();
+    }
+});
@@ -0,0 +1,46 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wtx_tools::{GrooveStyle, LineEndCap, LineStyle, SegmentKind};
+
+#[derive(Debug, arbitrary::Arbitrary)]
+struct Input {
+    normal: bool,
+    points: Vec<(f32, f32)>,
+    segments: Option<Vec<u8>>,
+    thickness: f32,
+    symmetry: i32,
+    color: (u8, u8, u8),
+    opacity: f32,
+    blur: f32,
+    start_radius: f32,
+    nub: bool,
+    depth: f32,
+    bevel: f32,
+}
+
+fuzz_target!(|input: Input| {
+    let segments: Option<Vec<SegmentKind>> = input.segments.map(|kinds| kinds.iter().map(|kind| match kind % 5 {
+        0 => SegmentKind::Line,
+        1 => SegmentKind::Quad,
+        2 => SegmentKind::Cubic,
+        3 => SegmentKind::ArcClockwise,
+        _ => SegmentKind::ArcCounterClockwise,
+    }).collect());
+    let style = LineStyle {
+        r: input.color.0,
+        g: input.color.1,
+        b: input.color.2,
+        opacity: input.opacity,
+        blur: input.blur,
+        start_radius: input.start_radius,
+        end_cap: if input.nub { LineEndCap::Nub } else { LineEndCap::Round },
+    };
+    let _ = match input.normal {
+        false => wtx_tools::desert_spec_wtx(&input.points, segments.as_deref(), input.thickness, input.symmetry, &style),
+        true => {
+            let groove = GrooveStyle { depth: input.depth, bevel: input.bevel };
+            wtx_tools::desert_normal_wtx(&input.points, segments.as_deref(), input.thickness, input.symmetry, &style, &groove)
+        },
+    };
+});
@@ -0,0 +1,33 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wtx_tools::ColorPanelBackground;
+
+#[derive(Debug, arbitrary::Arbitrary)]
+struct Input {
+    width: u8,
+    /// when set, the grid is cut to exactly `width` columns so most inputs get past the size check
+    exact: bool,
+    background: u8,
+    grid: Vec<u32>,
+}
+
+fuzz_target!(|input: Input| {
+    let width = input.width as usize;
+    let (grid, height) = match (input.exact, width) {
+        (true, 0) => (&input.grid[..0], 0),
+        (true, _) => {
+            let height = input.grid.len() / width;
+            (&input.grid[..width * height], height)
+        },
+        (false, _) => (&input.grid[..], input.grid.len() / width.max(1)),
+    };
+    let background = match input.background % 5 {
+        0 => ColorPanelBackground::Blueprint,
+        1 => ColorPanelBackground::White,
+        2 => ColorPanelBackground::LightGrey,
+        3 => ColorPanelBackground::DarkGrey,
+        _ => ColorPanelBackground::Elevator,
+    };
+    let _ = wtx_tools::colorpanel_from_grid(grid, width, height, background);
+});
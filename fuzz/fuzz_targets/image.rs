@@ -0,0 +1,29 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wtx_tools::{WtxEncodeOptions, WtxFlags, WtxFormat};
+
+#[derive(Debug, arbitrary::Arbitrary)]
+struct Input {
+    mipmaps: bool,
+    format: u8,
+    flags: u8,
+    file: Vec<u8>,
+}
+
+fuzz_target!(|input: Input| {
+    let options = WtxEncodeOptions {
+        mipmaps: input.mipmaps,
+        format: match input.format % 3 {
+            0 => WtxFormat::DXT5,
+            1 => WtxFormat::DXT1,
+            _ => WtxFormat::Auto,
+        },
+        flags: WtxFlags::from_bits_retain(input.flags),
+        ..Default::default()
+    };
+    if let Ok(wtx) = wtx_tools::image_file_to_wtx(&input.file, &options) {
+        // anything we write should read back
+        wtx_tools::decode_wtx(&wtx).unwrap();
+    }
+});
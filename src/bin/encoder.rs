@@ -1,14 +1,36 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use color_eyre::eyre::Result;
 use image::io::Reader as ImageReader;
-use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
 use std::fs::File;
 use std::io::prelude::*;
 
 
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    encode: EncodeArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a panel description to a .wtx texture and a .png preview
+    Render(RenderArgs),
+    /// Print the header of .wtx files
+    Info(InfoArgs),
+    /// Build every texture listed in a texture pack manifest, and an index of them
+    Pack(PackArgs),
+    /// List or extract files from one of the game's asset packages (data-pc.zip)
+    Extract(ExtractArgs),
+}
+
+#[derive(Args)]
+struct EncodeArgs {
     #[arg(
         long,
         default_value_t = false,
@@ -17,61 +39,807 @@ struct Cli {
     include_mipmaps: bool,
     #[arg(
         long,
-        default_value_t = String::from("DXT5"),
-        help = "format (either DXT5 or DXT1)"
+        value_enum,
+        default_value_t = Format::DXT5,
+        help = "texture format"
+    )]
+    format: Format,
+    #[arg(
+        long,
+        value_enum,
+        help = "set a flag in the header. can be repeated. color textures usually want srgb"
+    )]
+    flag: Vec<Flag>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Quality::Fast,
+        help = "compression quality. slower is better quality"
+    )]
+    quality: Quality,
+    #[arg(
+        long,
+        help = "scale images to this size (e.g. 1024x512) before encoding"
+    )]
+    resize: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        help = "round the size up"
     )]
-    format: String,
+    round_size: Option<RoundSize>,
     #[arg(
         long,
-        default_value_t = 0,
-        help = "value of header byte 20 (some weird bitflags)"
+        value_enum,
+        default_value_t = RoundBy::Scale,
+        help = "how --round-size makes the image bigger"
+    )]
+    round_by: RoundBy,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Filter::CatmullRom,
+        help = "filter used for scaling"
+    )]
+    filter: Filter,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Average::SrgbMean,
+        help = "how to compute the average color in the header"
+    )]
+    average: Average,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "store the image's real average alpha in the header instead of 1.0"
+    )]
+    average_alpha: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["average", "average_alpha"],
+        help = "store this average color in the header instead of computing it, as 4 floats from 0 to 1 (e.g. 0.5,0.5,0.5,1)"
+    )]
+    average_color: Option<String>,
+    #[arg(
+        long,
+        help = "convert every image (and .json panel description) in this directory and its subdirectories"
+    )]
+    input_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        requires = "input_dir",
+        help = "where to write files from --input-dir, keeping their relative paths. defaults to next to the inputs"
+    )]
+    output_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        requires = "input_dir",
+        help = "only convert files from --input-dir matching this glob (e.g. \"*.png\", \"panels/**/*.json\"). can be repeated. \
+                globs without a / are matched against the file name, others against the path relative to --input-dir"
+    )]
+    include: Vec<String>,
+    #[arg(
+        long,
+        requires = "input_dir",
+        help = "skip files from --input-dir matching this glob. can be repeated"
+    )]
+    exclude: Vec<String>,
+    #[arg(
+        long,
+        value_enum,
+        help = "skip files whose .wtx is already up to date, judged by modification time or by a hash of the source and settings"
+    )]
+    incremental: Option<Incremental>,
+    #[arg(
+        short,
+        long,
+        help = "how many files to encode at once. defaults to the number of CPUs"
+    )]
+    jobs: Option<usize>,
+    #[arg(
+        short,
+        long,
+        conflicts_with = "input_dir",
+        help = "where to write the .wtx when converting a single file. use - to write to stdout"
+    )]
+    output: Option<PathBuf>,
+    #[arg(
+        long,
+        default_value_t = String::from("{stem}.wtx"),
+        help = "file name to give each output. {stem} is the input file name without extension, {name} with it and {ext} just the extension"
+    )]
+    name_template: String,
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with = "no_clobber",
+        help = "with --incremental, write outputs even when they are up to date. existing outputs are always overwritten without --incremental or --no-clobber"
+    )]
+    force: bool,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "never overwrite an existing output file"
     )]
-    bits: u8,
+    no_clobber: bool,
+    #[arg(help = "images to convert. .json files are read as panel descriptions and rendered instead")]
     files: Vec<String>,
 }
 
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    #[value(name = "DXT5", alias = "dxt5")]
+    DXT5,
+    #[value(name = "DXT1", alias = "dxt1")]
+    DXT1,
+    /// DXT1 for opaque images, DXT5 for anything with transparency
+    Auto,
+}
+
+impl From<Format> for wtx_tools::WtxFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::DXT5 => wtx_tools::WtxFormat::DXT5,
+            Format::DXT1 => wtx_tools::WtxFormat::DXT1,
+            Format::Auto => wtx_tools::WtxFormat::Auto,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Quality {
+    /// the compressor's blocks as they are
+    Fast,
+    /// refit each block's endpoints to the pixels
+    Normal,
+    /// also search around the endpoints. much slower
+    Slow,
+}
+
+impl From<Quality> for wtx_tools::WtxQuality {
+    fn from(quality: Quality) -> Self {
+        match quality {
+            Quality::Fast => wtx_tools::WtxQuality::Fast,
+            Quality::Normal => wtx_tools::WtxQuality::Normal,
+            Quality::Slow => wtx_tools::WtxQuality::Slow,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum RoundSize {
+    /// to a power of two
+    Pow2,
+    /// to whole DXT blocks
+    #[value(name = "multiple-of-4")]
+    MultipleOf4,
+}
+
+impl From<RoundSize> for wtx_tools::SizeRounding {
+    fn from(round: RoundSize) -> Self {
+        match round {
+            RoundSize::Pow2 => wtx_tools::SizeRounding::PowerOfTwo,
+            RoundSize::MultipleOf4 => wtx_tools::SizeRounding::MultipleOf4,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum RoundBy {
+    /// scale the image up
+    Scale,
+    /// add transparent space on the right and bottom
+    Pad,
+}
+
+impl From<RoundBy> for wtx_tools::SizeAdjust {
+    fn from(round_by: RoundBy) -> Self {
+        match round_by {
+            RoundBy::Scale => wtx_tools::SizeAdjust::Scale,
+            RoundBy::Pad => wtx_tools::SizeAdjust::Pad,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Filter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+impl From<Filter> for wtx_tools::ResizeFilter {
+    fn from(filter: Filter) -> Self {
+        match filter {
+            Filter::Nearest => wtx_tools::ResizeFilter::Nearest,
+            Filter::Triangle => wtx_tools::ResizeFilter::Triangle,
+            Filter::CatmullRom => wtx_tools::ResizeFilter::CatmullRom,
+            Filter::Gaussian => wtx_tools::ResizeFilter::Gaussian,
+            Filter::Lanczos3 => wtx_tools::ResizeFilter::Lanczos3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Average {
+    /// plain mean of the pixel values
+    SrgbMean,
+    /// mean in linear light, stored as linear values
+    LinearMean,
+    /// mean weighted by alpha, so transparent pixels don't count
+    AlphaWeighted,
+}
+
+impl From<Average> for wtx_tools::AverageMode {
+    fn from(average: Average) -> Self {
+        match average {
+            Average::SrgbMean => wtx_tools::AverageMode::SrgbMean,
+            Average::LinearMean => wtx_tools::AverageMode::LinearMean,
+            Average::AlphaWeighted => wtx_tools::AverageMode::AlphaWeighted,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Flag {
+    /// texture holds sRGB colors
+    Srgb,
+    /// texture is a specular map
+    Specular,
+}
+
+impl From<Flag> for wtx_tools::WtxFlags {
+    fn from(flag: Flag) -> Self {
+        match flag {
+            Flag::Srgb => wtx_tools::WtxFlags::SRGB,
+            Flag::Specular => wtx_tools::WtxFlags::SPECULAR,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Incremental {
+    /// the .wtx is newer than its source
+    Mtime,
+    /// the source and settings hash the same as when the .wtx was written
+    Hash,
+}
+
+#[derive(Args)]
+struct RenderArgs {
+    #[arg(
+        default_value_t = String::from("-"),
+        help = "panel description (.json) to render. use - to read from stdin"
+    )]
+    input: String,
+    #[arg(
+        short,
+        long,
+        help = "where to write the .wtx. the preview is written next to it as .png. defaults to the input path with the extension swapped. \
+                use - to write the .wtx to stdout, without a preview"
+    )]
+    output: Option<PathBuf>,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "only write the .wtx, without a .png preview"
+    )]
+    no_preview: bool,
+}
+
+#[derive(Args)]
+struct PackArgs {
+    #[arg(help = "manifest listing the textures (.toml or .json). see src/pack.rs for the format")]
+    manifest: PathBuf,
+    #[arg(
+        long,
+        help = "where to write the textures and the index, instead of the manifest's output_dir"
+    )]
+    output_dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct ExtractArgs {
+    #[arg(help = "asset package to read")]
+    package: PathBuf,
+    #[arg(help = "only use files whose path in the package matches one of these globs (e.g. \"*.wtx\", \"textures/obj_*\"). defaults to everything")]
+    patterns: Vec<String>,
+    #[arg(
+        short,
+        long,
+        default_value_t = false,
+        help = "list the matching files and their sizes instead of extracting them"
+    )]
+    list: bool,
+    #[arg(
+        short,
+        long,
+        default_value = ".",
+        help = "directory to extract into. files keep their path inside the package"
+    )]
+    output_dir: PathBuf,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "also decode each extracted .wtx to a .png next to it"
+    )]
+    png: bool,
+}
+
+#[derive(Args)]
+struct InfoArgs {
+    #[arg(required = true, help = ".wtx files to inspect")]
+    files: Vec<PathBuf>,
+}
+
 fn main() -> Result<()> {
     color_eyre::install()?;
-    let args = Cli::parse();
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Render(args)) => render(args),
+        Some(Command::Info(args)) => info(args),
+        Some(Command::Pack(args)) => pack(args),
+        Some(Command::Extract(args)) => extract(args),
+        None => encode(cli.encode),
+    }
+}
+
+fn info(args: InfoArgs) -> Result<()> {
+    for (i, path) in args.files.iter().enumerate() {
+        let mut header = Vec::new();
+        File::open(path)?.take(wtx_tools::WtxHeader::LEN as u64).read_to_end(&mut header)?;
+        let header = wtx_tools::WtxHeader::parse(&header)?;
+        if args.files.len() > 1 {
+            if i > 0 {
+                println!();
+            }
+            println!("{}:", path.display());
+        }
+        println!("{}", header);
+    }
+    Ok(())
+}
+
+fn pack(args: PackArgs) -> Result<()> {
+    let mut manifest = wtx_tools::pack::PackManifest::load(&args.manifest)?;
+    let base_dir = args.manifest.parent().unwrap_or(Path::new(""));
+    if let Some(output_dir) = args.output_dir {
+        // relative to where the encoder is run, not to the manifest
+        manifest.output_dir = Some(std::env::current_dir()?.join(output_dir));
+    }
+    let index = wtx_tools::pack::build_pack(&manifest, base_dir)?;
+    for entry in &index.textures {
+        eprintln!("Built {:?} ({}x{} {:?}, {} bytes)", entry.name, entry.width, entry.height, entry.format, entry.size);
+    }
+    eprintln!("Saved {} textures and {:?}", index.textures.len(), manifest.index_path(base_dir));
+    Ok(())
+}
+
+fn extract(args: ExtractArgs) -> Result<()> {
+    let mut package = wtx_tools::package::AssetPackage::open(&args.package)?;
+    let patterns = args.patterns.iter().map(|p| glob::Pattern::new(p)).collect::<Result<Vec<_>, _>>()?;
+    let entries: Vec<_> = package.entries().iter()
+        .filter(|entry| patterns.is_empty() || patterns.iter().any(|p| p.matches(&entry.name)))
+        .cloned()
+        .collect();
+
+    if args.list {
+        for entry in &entries {
+            println!("{:>10} {:>10}  {}", entry.size, entry.compressed_size, entry.name);
+        }
+        eprintln!("{} of {} files", entries.len(), package.entries().len());
+        return Ok(());
+    }
+
+    let mut extracted = 0;
+    for entry in entries.iter().filter(|entry| !entry.is_dir()) {
+        let path = package.extract_to(entry, &args.output_dir)?;
+        eprintln!("Saved {:?}", path);
+        if args.png && entry.is_wtx() {
+            // one .wtx that doesn't decode shouldn't stop the rest
+            let png_path = path.with_extension("png");
+            match wtx_tools::decode_wtx(&std::fs::read(&path)?) {
+                Ok(img) => {
+                    img.save(&png_path)?;
+                    eprintln!("Saved {:?}", png_path);
+                },
+                Err(e) => eprintln!("could not decode {:?} for a preview: {}", path, e),
+            }
+        }
+        extracted += 1;
+    }
+    eprintln!("extracted {} of {} files", extracted, package.entries().len());
+    Ok(())
+}
+
+fn render(args: RenderArgs) -> Result<()> {
+    // before reading stdin, so a missing --output doesn't wait for the whole input first
+    let wtx_path = match (args.output, args.input.as_str()) {
+        (Some(output), _) => output,
+        (None, "-") => color_eyre::eyre::bail!("--output is required when reading from stdin"),
+        (None, input) => PathBuf::from(input).with_extension("wtx"),
+    };
+    let json = match args.input.as_str() {
+        "-" => {
+            let mut json = String::new();
+            std::io::stdin().read_to_string(&mut json)?;
+            json
+        },
+        path => std::fs::read_to_string(path)?,
+    };
+    let description = wtx_tools::description::PanelDescription::from_json(&json)?;
+
+    let img = description.render_image()?;
+    let to_stdout = is_stdout(&wtx_path);
+    if !args.no_preview && !to_stdout {
+        let preview_path = wtx_path.with_extension("png");
+        description.preview(&img).save(&preview_path)?;
+        eprintln!("Saved {:?}", preview_path);
+    }
+    let bytes = description.encode(img)?;
+    write_output(&wtx_path, &bytes)?;
+    if !to_stdout {
+        eprintln!("Saved {:?}", wtx_path);
+    }
+    Ok(())
+}
+
+/// one file to convert
+struct Job {
+    input: PathBuf,
+    output: PathBuf,
+}
+
+enum Outcome {
+    /// carries the hash of the source, for the hash cache
+    Written(String),
+    UpToDate,
+    /// not written because of --no-clobber
+    Exists,
+}
+
+/// when an existing output may be replaced
+#[derive(Clone, Copy, PartialEq)]
+enum Overwrite {
+    /// unless --incremental says it's up to date
+    IfOutdated,
+    /// --force, which skips the --incremental check
+    Always,
+    /// --no-clobber
+    Never,
+}
+
+fn encode(args: EncodeArgs) -> Result<()> {
+    // encoding settings shared by every file in a batch
+    let settings = wtx_tools::WtxEncodeOptions {
+        mipmaps: args.include_mipmaps,
+        format: args.format.into(),
+        flags: args.flag.iter().fold(wtx_tools::WtxFlags::empty(), |flags, &flag| flags | flag.into()),
+        quality: args.quality.into(),
+        resize: args.resize.as_deref().map(parse_size).transpose()?,
+        rounding: args.round_size.map_or(wtx_tools::SizeRounding::None, Into::into),
+        adjust: args.round_by.into(),
+        filter: args.filter.into(),
+        average: args.average.into(),
+        average_alpha: args.average_alpha,
+        average_override: args.average_color.as_deref().map(parse_color).transpose()?,
+    };
+
+    let mut jobs: Vec<Job> = args.files.iter().map(|f| Ok(Job {
+        input: PathBuf::from(f),
+        output: Path::new(f).with_file_name(output_name(Path::new(f), &args.name_template)?),
+    })).collect::<Result<_>>()?;
+    if let Some(input_dir) = &args.input_dir {
+        let output_dir = args.output_dir.as_ref().unwrap_or(input_dir);
+        let include = args.include.iter().map(|p| glob::Pattern::new(p)).collect::<Result<Vec<_>, _>>()?;
+        let exclude = args.exclude.iter().map(|p| glob::Pattern::new(p)).collect::<Result<Vec<_>, _>>()?;
+        collect_jobs(input_dir, input_dir, output_dir, &include, &exclude, &args.name_template, &mut jobs)?;
+    }
+    if let Some(output) = &args.output {
+        if jobs.len() != 1 {
+            color_eyre::eyre::bail!("--output can only be used when converting a single file");
+        }
+        jobs[0].output = output.clone();
+    }
+    check_outputs(&jobs)?;
+    let overwrite = match (args.force, args.no_clobber) {
+        (_, true) => Overwrite::Never,
+        (true, _) => Overwrite::Always,
+        _ => Overwrite::IfOutdated,
+    };
+
+    let mut hashes = HashCache::default();
+    let previous_hashes = match args.incremental {
+        Some(Incremental::Hash) => jobs.iter().map(|job| hashes.get(&job.output)).collect::<Result<Vec<_>>>()?,
+        _ => vec![None; jobs.len()],
+    };
+
+    //convert everything first, then report in order, so the output doesn't depend on which thread finished first
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(args.jobs.unwrap_or(0)).build()?;
+    let results: Vec<Result<Outcome>> = pool.install(|| {
+        jobs.par_iter()
+            .zip(&previous_hashes)
+            .map(|(job, previous_hash)| convert_job(job, &settings, args.incremental, overwrite, previous_hash.as_deref()))
+            .collect()
+    });
+
     let mut errs = vec![];
-    for arg in &args.files {
-        let path = Path::new(&arg);
-        let enumformat = match args.format.as_str() {
-            "DXT1" => wtx_tools::WtxFormat::DXT1,
-            "DXT5" => wtx_tools::WtxFormat::DXT5,
-            _ => panic!("unsupported format"),
-        };
-        let result = convert_to_wtx(&path, args.include_mipmaps, enumformat, args.bits);
+    let mut skipped = 0;
+    for (job, result) in std::iter::zip(&jobs, results) {
         match result {
-            Ok(_) => println!("success"),
+            Ok(Outcome::Written(hash)) => {
+                if !is_stdout(&job.output) {
+                    eprintln!("Saved {:?}", job.output);
+                }
+                if args.incremental == Some(Incremental::Hash) {
+                    hashes.set(&job.output, hash)?;
+                }
+            },
+            Ok(Outcome::UpToDate) => {
+                eprintln!("{:?} is up to date, skipping", job.output);
+                skipped += 1;
+            },
+            Ok(Outcome::Exists) => {
+                eprintln!("{:?} already exists, not overwriting it", job.output);
+                skipped += 1;
+            },
             Err(e) => {
-                println!("fail {:?}", e);
+                eprintln!("failed to convert {:?}: {:?}", job.input, e);
                 errs.push(e);
             }
         }
     }
-    println!(
-        "finished processing {} files. {} failed to process.",
-        args.files.len(),
+    hashes.save()?;
+    eprintln!(
+        "finished processing {} files. {} were skipped. {} failed to process.",
+        jobs.len(),
+        skipped,
         errs.len()
     );
+    if !errs.is_empty() {
+        color_eyre::eyre::bail!("{} of {} files failed to process", errs.len(), jobs.len());
+    }
     Ok(())
 }
 
-fn convert_to_wtx(
-    filename: &std::path::Path,
-    gen_mipmaps: bool,
-    format: wtx_tools::WtxFormat,
-    bits: u8,
+/// walks `dir` recursively, adding a job for every matching file.
+/// outputs keep their path relative to `input_root`, under `output_root`.
+fn collect_jobs(
+    dir: &Path,
+    input_root: &Path,
+    output_root: &Path,
+    include: &[glob::Pattern],
+    exclude: &[glob::Pattern],
+    name_template: &str,
+    jobs: &mut Vec<Job>,
 ) -> Result<()> {
-    let img = ImageReader::open(filename)?.decode()?;
+    let mut entries = std::fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|e| e.path()); //so batches are processed in the same order every time
+    for entry in entries {
+        let path = entry.path();
+        // hidden files are never textures, and the hash cache is one of them, written next to the outputs
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            collect_jobs(&path, input_root, output_root, include, exclude, name_template, jobs)?;
+            continue;
+        }
+        let relative = path.strip_prefix(input_root)?;
+        let matches = |pattern: &glob::Pattern| match pattern.as_str().contains('/') {
+            true => pattern.matches_path(relative),
+            false => pattern.matches(&entry.file_name().to_string_lossy()),
+        };
+        let included = match include.is_empty() {
+            true => is_convertible(&path),
+            false => include.iter().any(matches),
+        };
+        if included && !exclude.iter().any(matches) {
+            jobs.push(Job {
+                input: path.clone(),
+                output: output_root.join(relative).with_file_name(output_name(&path, name_template)?),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// fails if two jobs would write the same file, which with parallel jobs could leave either one's output,
+/// e.g. `a.png` and `a.json` both make `a.wtx`
+fn check_outputs(jobs: &[Job]) -> Result<()> {
+    let mut outputs: HashMap<PathBuf, &Path> = HashMap::new();
+    for job in jobs {
+        if let Some(other) = outputs.insert(normalize(&job.output)?, &job.input) {
+            color_eyre::eyre::bail!("{:?} and {:?} would both be written to {:?}", other, job.input, job.output);
+        }
+    }
+    Ok(())
+}
+
+/// `path` made absolute, with `.` and `..` resolved, so different ways of writing the same output compare equal.
+/// doesn't look at the file system, as outputs usually don't exist yet
+fn normalize(path: &Path) -> Result<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in std::env::current_dir()?.join(path).components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => {
+                normalized.pop();
+            },
+            component => normalized.push(component),
+        }
+    }
+    Ok(normalized)
+}
+
+/// fills in `--name-template` for an input file
+fn output_name(input: &Path, template: &str) -> Result<String> {
+    let part = |s: Option<&std::ffi::OsStr>| s.map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = template
+        .replace("{stem}", &part(input.file_stem()))
+        .replace("{name}", &part(input.file_name()))
+        .replace("{ext}", &part(input.extension()));
+    if name.is_empty() || name.contains(['/', '\\']) {
+        color_eyre::eyre::bail!("--name-template gave {:?} for {:?}, which isn't a file name", name, input);
+    }
+    Ok(name)
+}
 
-    let bytes = wtx_tools::generate_wtx_from_image(img.to_rgba8(), gen_mipmaps, format, bits);
+/// whether a file is something the encoder can read: an image or a panel description
+fn is_convertible(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => true,
+        Some(ext) => image::ImageFormat::from_extension(ext).is_some(),
+        None => false,
+    }
+}
 
-    let mut newpath = PathBuf::from(filename);
-    assert_eq!(newpath.set_extension("wtx"), true);
-    let mut file = File::create(&newpath)?;
-    file.write_all(&bytes)?;
-    println!("Saved {:?}", newpath);
+/// converts a single file. `previous_hash` is what the hash cache has for the output, if anything
+fn convert_job(job: &Job, settings: &wtx_tools::WtxEncodeOptions, incremental: Option<Incremental>, overwrite: Overwrite, previous_hash: Option<&str>) -> Result<Outcome> {
+    let source = std::fs::read(&job.input)?;
+    let hash = source_hash(&source, settings)?;
+    let exists = !is_stdout(&job.output) && job.output.exists();
+    if overwrite == Overwrite::Never && exists {
+        return Ok(Outcome::Exists);
+    }
+    let up_to_date = match incremental {
+        _ if !exists || overwrite == Overwrite::Always => false,
+        Some(Incremental::Mtime) => std::fs::metadata(&job.output)?.modified()? >= std::fs::metadata(&job.input)?.modified()?,
+        Some(Incremental::Hash) => previous_hash == Some(hash.as_str()),
+        None => false,
+    };
+    if up_to_date {
+        return Ok(Outcome::UpToDate);
+    }
+
+    let bytes = match job.input.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => render_description(&source)?,
+        _ => convert_to_wtx(&source, settings)?,
+    };
+    write_output(&job.output, &bytes)?;
+    Ok(Outcome::Written(hash))
+}
+
+/// whether an output path is -, meaning stdout
+fn is_stdout(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// writes to `path`, creating its directory if needed. a path of - means stdout
+fn write_output(path: &Path, bytes: &[u8]) -> Result<()> {
+    if is_stdout(path) {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(bytes)?;
+        stdout.flush()?;
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = File::create(path)?;
+    file.write_all(bytes)?;
     Ok(())
 }
+
+fn convert_to_wtx(source: &[u8], settings: &wtx_tools::WtxEncodeOptions) -> Result<Vec<u8>> {
+    let img = ImageReader::new(std::io::Cursor::new(source)).with_guessed_format()?.decode()?;
+
+    Ok(wtx_tools::encode_wtx(img.to_rgba8(), settings)?)
+}
+
+/// parses a color like "0.5,0.5,0.5,1"
+fn parse_color(color: &str) -> Result<[f32; 4]> {
+    let channels = color.split(',').map(|c| c.trim().parse()).collect::<Result<Vec<f32>, _>>()?;
+    match channels[..] {
+        [r, g, b, a] => Ok([r, g, b, a]),
+        _ => color_eyre::eyre::bail!("color should be 4 numbers like 0.5,0.5,0.5,1, not {:?}", color),
+    }
+}
+
+/// parses a size like "1024x512"
+fn parse_size(size: &str) -> Result<(u32, u32)> {
+    let Some((width, height)) = size.split_once('x') else {
+        color_eyre::eyre::bail!("size should look like 1024x512, not {:?}", size);
+    };
+    Ok((width.parse()?, height.parse()?))
+}
+
+fn render_description(source: &[u8]) -> Result<Vec<u8>> {
+    let description = wtx_tools::description::PanelDescription::from_json(std::str::from_utf8(source)?)?;
+
+    Ok(description.render()?)
+}
+
+/// hash of the source file and the settings it is encoded with.
+/// every setting is written out in a fixed order, enums by the names panel descriptions use for them,
+/// so the hash only changes when a setting does. a new field in `WtxEncodeOptions` won't compile until it's added here
+fn source_hash(source: &[u8], settings: &wtx_tools::WtxEncodeOptions) -> Result<String> {
+    fn name<T: serde::Serialize>(value: T) -> Result<String> {
+        Ok(serde_json::to_string(&value)?)
+    }
+    let wtx_tools::WtxEncodeOptions { mipmaps, format, flags, quality, resize, rounding, adjust, filter, average, average_alpha, average_override } = *settings;
+    let settings = [
+        format!("mipmaps {}", mipmaps),
+        format!("format {}", name(format)?),
+        format!("flags {}", flags.bits()),
+        format!("quality {}", name(quality)?),
+        format!("resize {}", resize.map_or("none".to_string(), |(width, height)| format!("{}x{}", width, height))),
+        format!("rounding {}", name(rounding)?),
+        format!("adjust {}", name(adjust)?),
+        format!("filter {}", name(filter)?),
+        format!("average {}", name(average)?),
+        format!("average_alpha {}", average_alpha),
+        format!("average_color {}", average_override.map_or("none".to_string(), |color| color.map(|c| c.to_string()).join(","))),
+    ].join("\n");
+    Ok(wtx_tools::hash::fnv1a(&[source, settings.as_bytes()]))
+}
+
+const HASH_CACHE_NAME: &str = ".encoder-hashes.json";
+
+/// hashes of the sources each output was made from, for `--incremental hash`.
+/// stored as one file per output directory, mapping output file names to hashes.
+#[derive(Default)]
+struct HashCache {
+    dirs: HashMap<PathBuf, (HashMap<String, String>, bool)>,
+}
+
+impl HashCache {
+    fn dir_entries(&mut self, output: &Path) -> Result<(&mut HashMap<String, String>, &mut bool, String)> {
+        let dir = output.parent().unwrap_or(Path::new("")).to_path_buf();
+        let name = output.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        if !self.dirs.contains_key(&dir) {
+            let entries = match std::fs::read_to_string(dir.join(HASH_CACHE_NAME)) {
+                Ok(json) => serde_json::from_str(&json)?,
+                Err(_) => HashMap::new(),
+            };
+            self.dirs.insert(dir.clone(), (entries, false));
+        }
+        let (entries, dirty) = self.dirs.get_mut(&dir).unwrap();
+        Ok((entries, dirty, name))
+    }
+
+    fn get(&mut self, output: &Path) -> Result<Option<String>> {
+        let (entries, _, name) = self.dir_entries(output)?;
+        Ok(entries.get(&name).cloned())
+    }
+
+    fn set(&mut self, output: &Path, hash: String) -> Result<()> {
+        let (entries, dirty, name) = self.dir_entries(output)?;
+        entries.insert(name, hash);
+        *dirty = true;
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        for (dir, (entries, dirty)) in &self.dirs {
+            if *dirty {
+                std::fs::write(dir.join(HASH_CACHE_NAME), serde_json::to_string_pretty(entries)?)?;
+            }
+        }
+        Ok(())
+    }
+}
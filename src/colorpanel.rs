@@ -0,0 +1,211 @@
+//! Color bunker panels: colored squares drawn over one of the game's backgrounds.
+//! Needs the `render` feature.
+
+use image::{ImageBuffer, Pixel, Rgba};
+use raqote::*;
+use serde::{Deserialize, Serialize};
+use crate::wtx::{encode_wtx_image, WtxEncodeOptions, WtxFlags, WtxFormat};
+use crate::Error;
+
+#[repr(C)]
+// TODO remove this
+/// enum defining color of a 'stone'. Used internally.
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WtxColor {
+    NoColor,
+
+    TricolorWhite,
+    TricolorPurple,
+    TricolorGreen,
+
+    TricolorNewWhite,
+    TricolorNewPink,
+    TricolorNewBlue,
+    TricolorNewYellow,
+}
+
+#[repr(u32)]
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// Enum used to decide which background to give a generated color-panel image
+pub enum ColorPanelBackground {
+    /// used on introductory puzzles
+    Blueprint, 
+    /// used in the 2 shipping container puzzles
+    White,
+    /// First 2 color-filter puzzles
+    LightGrey, 
+    /// third color-filter puzzle. Slightly darker gray    
+    DarkGrey, 
+    /// only used on elevator
+    Elevator,
+}
+
+impl TryFrom<u32> for ColorPanelBackground {
+    type Error = Error;
+
+    /// for values from C, which can be anything
+    fn try_from(value: u32) -> Result<ColorPanelBackground, Error> {
+        match value {
+            0 => Ok(ColorPanelBackground::Blueprint),
+            1 => Ok(ColorPanelBackground::White),
+            2 => Ok(ColorPanelBackground::LightGrey),
+            3 => Ok(ColorPanelBackground::DarkGrey),
+            4 => Ok(ColorPanelBackground::Elevator),
+            _ => Err(Error::UnknownEnumValue { name: "background", value }),
+        }
+    }
+}
+
+/// Safe version of `wtx_tools_generate_colorpanel_from_grid`, for use from Rust.
+/// `grid` is the flattened `_grid` of a panel and must hold `width * height` values.
+pub fn colorpanel_from_grid(grid: &[u32], width: usize, height: usize, background: ColorPanelBackground) -> Result<Vec<u8>, Error> {
+    generate_tricolor_panel_wtx(grid, width, height, background, None)
+}
+
+/// Reads the stones out of a flattened puzzle grid, row by row. Cells between lines without a stone are `NoColor`.
+pub fn stones_from_grid(gridflat: &[u32], width: usize, height: usize) -> Result<Vec<WtxColor>, Error> {
+    if width.checked_mul(height) != Some(gridflat.len()) {
+        return Err(Error::InvalidGrid { width, height, len: gridflat.len() });
+    }
+
+    let mut grid = Vec::<Vec<u32>>::new();
+    for i in 0..height {
+        let mut row = Vec::<u32>::new();
+        for j in 0..width {
+            row.push(gridflat[i + j*height]) // this is the problem
+        }
+        grid.push(row);
+    }
+    //now we have rebuilt a nice vector for the whole grid
+    let mut just_stones_vec = Vec::new(); //we want to ignore most of the grid - only look for the stones
+    for (rownum, row) in grid.into_iter().enumerate() {
+        for (colnum, cell) in row.into_iter().enumerate() {
+            if (rownum % 2 != 0) && (colnum % 2 != 0) {
+                //this is between two lines
+                if cell & 0x100 > 0 {
+                    //stone here
+                    just_stones_vec.push(match cell & 0xF {
+                        // 0x0 => WtxColor::NoColor,
+                        0x2 => WtxColor::TricolorWhite,
+                        0x4 => WtxColor::TricolorPurple,
+                        0x5 => WtxColor::TricolorGreen,
+                        // 0x6 => WtxColor::TricolorNewBlue, //CYAN
+                        0x7 => WtxColor::TricolorNewPink,
+                        0x8 => WtxColor::TricolorNewYellow,
+                        0x9 => WtxColor::TricolorNewBlue,
+                        _ => return Err(Error::UnknownStoneColor(cell)),
+                    })
+                } else {
+                    just_stones_vec.push(WtxColor::NoColor)
+                }
+            }
+        }
+    }
+    Ok(just_stones_vec)
+}
+
+
+/// Draws a color panel with one stone per entry of `stones`, row by row.
+/// Takes 9, 16 or 20 stones, for 3x3, 4x4 and 5x4 panels.
+pub fn colorpanel_image(stones: &[WtxColor], background: ColorPanelBackground) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error> {
+    generate_colordots_panel(stones, background, None)
+}
+
+/// Same as `colorpanel_image`, but encoded to a wtx texture the way the game expects it.
+pub fn colorpanel_wtx(stones: &[WtxColor], background: ColorPanelBackground) -> Result<Vec<u8>, Error> {
+    encode_wtx_image(colorpanel_image(stones, background)?, &colorpanel_options())
+}
+
+///Internal function to generate Imagebuffer from a slice of colors
+///This function takes shapes 3x3, 4x4, or 4x5
+fn generate_colordots_panel(stones: &[WtxColor], background: ColorPanelBackground, filename_id: Option<i32>) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error> {
+    // one match for everything that depends on the size, so there's no other case to get wrong
+    let (dot_coordinates, scale) = match stones.len() {
+        9  => (vec![(280.0,280.0),(512.0,280.0),(744.0,280.0),
+                    (280.0,512.0),(512.0,512.0),(744.0,512.0),
+                    (280.0,744.0),(512.0,744.0),(744.0,744.0)], 32.0),
+        16 => (vec![(238.0,238.0),(421.0,238.0),(604.0,238.0),(787.0,238.0),
+                    (238.0,421.0),(421.0,421.0),(604.0,421.0),(787.0,421.0),
+                    (238.0,604.0),(421.0,604.0),(604.0,604.0),(787.0,604.0),
+                    (238.0,787.0),(421.0,787.0),(604.0,787.0),(787.0,787.0),], 30.0),
+        20 => (vec![(212.0,288.0),(362.0,288.0),(512.0,288.0),(662.0,288.0),(812.0,288.0),
+                    (212.0,437.0),(362.0,437.0),(512.0,437.0),(662.0,437.0),(812.0,437.0),
+                    (212.0,586.0),(362.0,586.0),(512.0,586.0),(662.0,586.0),(812.0,586.0),
+                    (212.0,736.0),(362.0,736.0),(512.0,736.0),(662.0,736.0),(812.0,736.0)], 20.0),
+        count => return Err(Error::UnsupportedStoneCount(count)),
+    };
+    let mut dt: DrawTarget = DrawTarget::new(1024, 1024);
+    for (coords, color) in std::iter::zip(dot_coordinates, stones.iter().copied()) {
+        if color != WtxColor::NoColor {
+            let realcolor = match color {
+                WtxColor::TricolorWhite => SolidSource{r: 0xff, g: 0xff, b:0xff, a:0xFF},
+                WtxColor::TricolorPurple => SolidSource{r: 0xa5, g: 0x51, b:0xff, a:0xFF},
+                WtxColor::TricolorGreen => SolidSource{r: 0x6e, g: 0xab, b:0x5d, a:0xFF},
+                WtxColor::TricolorNewWhite => SolidSource{r: 0xff, g: 0xff, b:0xff, a:0xFF},
+                WtxColor::TricolorNewPink => SolidSource{r: 0xa4, g: 0x37, b:0xf0, a:0xFF},
+                WtxColor::TricolorNewBlue => SolidSource{r: 0x00, g: 0xa8, b:0xe9, a:0xFF},
+                WtxColor::TricolorNewYellow => SolidSource{r: 0xf9, g: 0xf8, b:0x45, a:0xFF},
+                WtxColor::NoColor => unreachable!()
+            };
+
+            let mut pb = PathBuilder::new();
+            pb.move_to(coords.0 - 20.0, coords.1 - 20.0);
+            pb.line_to(coords.0 - 20.0, coords.1 + 20.0);
+            pb.line_to(coords.0 + 20.0, coords.1 + 20.0);
+            pb.line_to(coords.0 + 20.0, coords.1 - 20.0);
+            pb.line_to(coords.0 - 20.0, coords.1 - 20.0);
+            pb.close();
+            let path = pb.finish();
+            dt.fill(&path, &Source::Solid(realcolor), &DrawOptions::new());
+            dt.stroke(&path, &Source::Solid(realcolor),&StrokeStyle {
+                cap: LineCap::Round,
+                join: LineJoin::Round,
+                width: scale,
+                miter_limit: 2.,
+                dash_array: vec![50.0, 0.0],
+                dash_offset: 0.0,
+            }, &DrawOptions::new());
+            // println!("[rust] placed a dot");
+        }
+    }
+    let mut img_of_dots: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_raw(1024,1024,dt.get_data_u8().to_vec()).unwrap();
+    for pixel in img_of_dots.pixels_mut() {
+        pixel.channels_mut().swap(0, 2); //fix pixel order
+    }
+
+    let bg_img_bytes: &[u8] = match background {
+        ColorPanelBackground::Blueprint => include_bytes!("images/color_bunker_blueprint_bg.png"),
+        ColorPanelBackground::White => include_bytes!("images/color_bunker_whitepaper.png"),
+        ColorPanelBackground::LightGrey => include_bytes!("images/color_bunker_greyred_light.png"),
+        ColorPanelBackground::DarkGrey => include_bytes!("images/color_bunker_greyred_dark.png"),
+        ColorPanelBackground::Elevator => include_bytes!("images/color_bunker_elevator.png"),
+    };
+    let mut bg_img = image::load_from_memory(bg_img_bytes).unwrap().to_rgba8();
+    image::imageops::overlay(&mut bg_img, &img_of_dots, 0, 0);
+
+    if let Some(id) = filename_id {
+        //save BEFORE we strip alpha channel
+        bg_img.save(format!("./generated_{:x}.png", id)).map_err(|e| match e {
+            image::ImageError::IoError(e) => Error::Io(e),
+            e => Error::EncodeFailed(e.to_string()),
+        })?;
+    }
+    for pixel in bg_img.pixels_mut() {
+        pixel.apply_with_alpha(|color| color, |_| 0);
+    }
+    // bg_img.save("/tmp/genimg.png").unwrap(); //debug preview
+    Ok(bg_img)
+}
+
+pub(crate) fn generate_tricolor_panel_wtx(grid: &[u32], width: usize, height: usize, background: ColorPanelBackground, filename_id : Option<i32>) -> Result<Vec<u8>, Error> {
+    let stoneslist = stones_from_grid(grid, width, height)?;
+    let img: ImageBuffer<Rgba<u8>, Vec<u8>>  = generate_colordots_panel(&stoneslist, background, filename_id)?;
+
+    encode_wtx_image(img, &colorpanel_options())
+}
+
+fn colorpanel_options() -> WtxEncodeOptions {
+    WtxEncodeOptions { mipmaps: true, format: WtxFormat::DXT5, flags: WtxFlags::SRGB, ..Default::default() }
+}
@@ -0,0 +1,263 @@
+//! Declarative descriptions of generated textures. Needs the `render` feature.
+//!
+//! A `PanelDescription` holds everything needed to render one texture, so the same
+//! puzzle can be handed to the library from Rust, from C as a JSON string, or to the `encoder` binary as a file.
+//!
+//! ```json
+//! {
+//!     "type": "color_panel",
+//!     "background": "blueprint",
+//!     "width": 3,
+//!     "height": 3,
+//!     "cells": [
+//!         { "x": 1, "y": 0, "decoration": "stone", "color": "tricolor_green" },
+//!         { "x": 0, "y": 1, "decoration": "stone", "color": "tricolor_purple" }
+//!     ],
+//!     "output": { "format": "DXT5", "mipmaps": true, "quality": "normal" }
+//! }
+//! ```
+//!
+//! ```json
+//! {
+//!     "type": "desert_spec",
+//!     "thickness": 30.0,
+//!     "symmetry": "vertical",
+//!     "lines": [
+//!         { "points": [[0.1, 0.1], [0.4, 0.1], [0.4, 0.4]] },
+//!         { "points": [[0.5, 0.5], [0.6, 0.6], [0.7, 0.5]], "segments": ["quad"] }
+//!     ]
+//! }
+//! ```
+
+use image::{ImageBuffer, Rgba};
+use serde::{Deserialize, Serialize};
+
+use crate::colorpanel::{self, ColorPanelBackground, WtxColor};
+use crate::desert::{self, GrooveStyle, LineStyle, SegmentKind, SpecPath};
+use crate::{AverageMode, Error, WtxEncodeOptions, WtxFlags, WtxFormat, WtxQuality};
+
+/// A texture to generate, and how to encode it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PanelDescription {
+    #[serde(flatten)]
+    pub panel: PanelKind,
+    /// encoding settings. anything left out uses the defaults for the kind of panel
+    #[serde(default)]
+    pub output: OutputOptions,
+}
+
+/// The kinds of texture that can be generated, with the puzzle data each one needs.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PanelKind {
+    /// colored stones on one of the color bunker backgrounds.
+    /// `width` x `height` is counted in cells, and must be 3x3, 4x4 or 5x4.
+    ColorPanel {
+        background: ColorPanelBackground,
+        width: usize,
+        height: usize,
+        #[serde(default)]
+        cells: Vec<CellDescription>,
+    },
+    /// specular map of a desert panel, with the solution drawn as lines
+    DesertSpec {
+        lines: Vec<LineDescription>,
+        thickness: f32,
+        #[serde(default)]
+        symmetry: Symmetry,
+        #[serde(default)]
+        style: LineStyle,
+    },
+    /// normal map of a desert panel, with the solution carved in as grooves
+    DesertNormal {
+        lines: Vec<LineDescription>,
+        thickness: f32,
+        #[serde(default)]
+        symmetry: Symmetry,
+        #[serde(default)]
+        style: LineStyle,
+        #[serde(default)]
+        groove: GrooveStyle,
+    },
+}
+
+/// A single decorated cell of a grid. Cells that aren't listed are left empty.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CellDescription {
+    /// column, counting from the left
+    pub x: usize,
+    /// row, counting from the top
+    pub y: usize,
+    pub decoration: Decoration,
+    pub color: WtxColor,
+}
+
+/// Symbols that can be drawn in a cell.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decoration {
+    Stone,
+}
+
+/// A line in 0.0 - 1.0 panel coordinates.
+/// Without `segments` the points are joined with straight lines. With them, each segment takes the points
+/// it needs in order, just like `generate_desert_spec_path`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LineDescription {
+    pub points: Vec<[f32; 2]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segments: Option<Vec<SegmentKind>>,
+}
+
+/// Symmetry of a desert panel. Matches the randomizers' Symmetry enum, minus the pillar options.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Symmetry {
+    #[default]
+    None,
+    Horizontal,
+    Vertical,
+    Rotational,
+    RotateLeft,
+    RotateRight,
+    #[serde(rename = "flip_xy")]
+    FlipXY,
+    #[serde(rename = "flip_neg_xy")]
+    FlipNegXY,
+    ParallelH,
+    ParallelV,
+    ParallelHFlip,
+    ParallelVFlip,
+}
+
+impl Symmetry {
+    /// the `int` value the C functions take for this symmetry
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// How to encode the generated image. `None` means the default for the panel kind.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputOptions {
+    pub format: Option<WtxFormat>,
+    pub mipmaps: Option<bool>,
+    /// e.g. `"SRGB | SPECULAR"`
+    pub flags: Option<WtxFlags>,
+    pub quality: Option<WtxQuality>,
+    pub average: Option<AverageMode>,
+    pub average_alpha: Option<bool>,
+    /// RGBA color to store in the header instead of computing one
+    pub average_color: Option<[f32; 4]>,
+}
+
+impl PanelDescription {
+    /// Reads a description, failing with `Error::InvalidDescription` if it isn't valid JSON or doesn't match the format.
+    pub fn from_json(json: &str) -> Result<PanelDescription, Error> {
+        serde_json::from_str(json).map_err(|e| Error::InvalidDescription(e.to_string()))
+    }
+
+    /// Writes the description as JSON.
+    /// This can't fail: every field is a plain struct, enum, number or string, and serde_json writes non-finite numbers as `null`.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("panel descriptions always serialize")
+    }
+
+    /// Renders the described texture to an image, without encoding it.
+    pub fn render_image(&self) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error> {
+        match &self.panel {
+            PanelKind::ColorPanel { background, width, height, cells } => {
+                // the stones are laid out by count, so 4x5 would be drawn as 5x4
+                if !matches!((width, height), (3, 3) | (4, 4) | (5, 4)) {
+                    return Err(Error::UnsupportedPanelSize { width: *width, height: *height });
+                }
+                let mut stones = vec![WtxColor::NoColor; width * height];
+                for cell in cells {
+                    if cell.x >= *width || cell.y >= *height {
+                        return Err(Error::CellOutsideGrid { x: cell.x, y: cell.y });
+                    }
+                    match cell.decoration {
+                        Decoration::Stone => stones[cell.y * width + cell.x] = cell.color,
+                    }
+                }
+                colorpanel::colorpanel_image(&stones, *background)
+            },
+            PanelKind::DesertSpec { lines, thickness, symmetry, style } => {
+                let paths = lines.iter().map(LineDescription::to_path).collect::<Result<Vec<SpecPath>, Error>>()?;
+                desert::generate_desert_spec_line_img(&paths, *thickness, symmetry.code(), style)
+            },
+            PanelKind::DesertNormal { lines, thickness, symmetry, style, groove } => {
+                let paths = lines.iter().map(LineDescription::to_path).collect::<Result<Vec<SpecPath>, Error>>()?;
+                desert::generate_desert_normal_img(&paths, *thickness, symmetry.code(), style, groove)
+            },
+        }
+    }
+
+    /// Renders the described texture and encodes it as a wtx file.
+    pub fn render(&self) -> Result<Vec<u8>, Error> {
+        self.encode(self.render_image()?)
+    }
+
+    /// Encodes an image from `render_image` as a wtx file, using the output settings of this description.
+    pub fn encode(&self, img: ImageBuffer<Rgba<u8>, Vec<u8>>) -> Result<Vec<u8>, Error> {
+        let (format, mipmaps, flags) = match self.panel {
+            PanelKind::ColorPanel { .. } => (WtxFormat::DXT5, true, WtxFlags::SRGB),
+            PanelKind::DesertSpec { .. } => (WtxFormat::DXT1, true, WtxFlags::SRGB | WtxFlags::SPECULAR),
+            PanelKind::DesertNormal { .. } => (WtxFormat::DXT1, true, WtxFlags::empty()),
+        };
+        let options = self.output.apply(WtxEncodeOptions { mipmaps, format, flags, ..Default::default() });
+        crate::encode_wtx(img, &options)
+    }
+
+    /// Makes a copy of an image from `render_image` that is viewable as a normal picture.
+    /// Color panels have their alpha cleared for the game, so this makes them opaque again.
+    pub fn preview(&self, img: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let mut preview = img.clone();
+        if let PanelKind::ColorPanel { .. } = self.panel {
+            for pixel in preview.pixels_mut() {
+                pixel[3] = 0xff;
+            }
+        }
+        preview
+    }
+}
+
+impl OutputOptions {
+    /// these options, with anything left out taken from `fallback`
+    pub fn or(self, fallback: OutputOptions) -> OutputOptions {
+        OutputOptions {
+            format: self.format.or(fallback.format),
+            mipmaps: self.mipmaps.or(fallback.mipmaps),
+            flags: self.flags.or(fallback.flags),
+            quality: self.quality.or(fallback.quality),
+            average: self.average.or(fallback.average),
+            average_alpha: self.average_alpha.or(fallback.average_alpha),
+            average_color: self.average_color.or(fallback.average_color),
+        }
+    }
+
+    /// `base`, with everything these options set replaced
+    pub fn apply(self, base: WtxEncodeOptions) -> WtxEncodeOptions {
+        WtxEncodeOptions {
+            mipmaps: self.mipmaps.unwrap_or(base.mipmaps),
+            format: self.format.unwrap_or(base.format),
+            flags: self.flags.unwrap_or(base.flags),
+            quality: self.quality.unwrap_or(base.quality),
+            average: self.average.unwrap_or(base.average),
+            average_alpha: self.average_alpha.unwrap_or(base.average_alpha),
+            average_override: self.average_color.or(base.average_override),
+            ..base
+        }
+    }
+}
+
+impl LineDescription {
+    fn to_path(&self) -> Result<SpecPath, Error> {
+        let points: Vec<(f32,f32)> = self.points.iter().map(|p| (p[0], p[1])).collect();
+        match &self.segments {
+            Some(kinds) => SpecPath::from_segments(&points, kinds),
+            None => SpecPath::from_polyline(&points),
+        }
+    }
+}
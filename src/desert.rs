@@ -0,0 +1,549 @@
+//! Desert panels: a line drawn onto the spec map, and the matching groove in the normal map.
+//! Needs the `render` feature.
+
+use image::{ImageBuffer, Luma, Pixel, Rgba};
+use raqote::*;
+use serde::{Deserialize, Serialize};
+use crate::wtx::{encode_wtx_image, WtxEncodeOptions, WtxFlags, WtxFormat};
+use crate::Error;
+
+#[repr(u32)]
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How the far end of a line on a desert spec map is drawn
+pub enum LineEndCap {
+    /// rounded end, like the rest of the line
+    Round,
+    /// flat end with a thinner rounded stub past it, like the exit nub on a panel
+    Nub,
+}
+
+impl TryFrom<u32> for LineEndCap {
+    type Error = Error;
+
+    /// for values from C, which can be anything
+    fn try_from(value: u32) -> Result<LineEndCap, Error> {
+        match value {
+            0 => Ok(LineEndCap::Round),
+            1 => Ok(LineEndCap::Nub),
+            _ => Err(Error::UnknownEnumValue { name: "end_cap", value }),
+        }
+    }
+}
+
+#[repr(u32)]
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// Kind of each segment in a curved desert spec map line.
+/// Segments are drawn one after another, each starting where the last one ended,
+/// and each uses up the listed number of points from the point arrays.
+pub enum SegmentKind {
+    /// straight line. 1 point: the end
+    Line,
+    /// quadratic curve. 2 points: the control point, then the end
+    Quad,
+    /// cubic curve. 3 points: both control points, then the end
+    Cubic,
+    /// clockwise arc. 2 points: the centre, then a point at the angle the arc should stop at.
+    /// The radius is the distance from the current position to the centre.
+    /// If the arc would stop where it started, a full circle is drawn.
+    ArcClockwise,
+    /// same as `ArcClockwise`, going the other way around
+    ArcCounterClockwise,
+}
+
+impl TryFrom<u32> for SegmentKind {
+    type Error = Error;
+
+    /// for values from C, which can be anything
+    fn try_from(value: u32) -> Result<SegmentKind, Error> {
+        match value {
+            0 => Ok(SegmentKind::Line),
+            1 => Ok(SegmentKind::Quad),
+            2 => Ok(SegmentKind::Cubic),
+            3 => Ok(SegmentKind::ArcClockwise),
+            4 => Ok(SegmentKind::ArcCounterClockwise),
+            _ => Err(Error::UnknownEnumValue { name: "segment kind", value }),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+/// Styling of the line drawn on a desert spec map.
+/// `LineStyle::default()` is the plain black line the other desert functions draw.
+/// C gets it as `ffi::LineStyle`, which has the same fields.
+pub struct LineStyle {
+    /// line color. spec maps are greyscale, so usually r == g == b, with black being no reflection
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    /// 0.0 - 1.0. lower values let more of the background specular through the line
+    pub opacity: f32,
+    /// sigma of the gaussian blur applied to the line. 0 disables blurring
+    pub blur: f32,
+    /// radius of the circle at the start of the line, as a multiple of the line thickness. 0 disables the circle
+    pub start_radius: f32,
+    pub end_cap: LineEndCap,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        LineStyle {
+            r: 0x00,
+            g: 0x00,
+            b: 0x00,
+            opacity: 1.0,
+            blur: 5.0,
+            start_radius: 0.5,
+            end_cap: LineEndCap::Round,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+/// Shape of the groove carved into a generated desert normal map.
+/// `GrooveStyle::default()` (or `wtx_tools_default_groove_style()` from C) is a reasonable starting point.
+pub struct GrooveStyle {
+    /// how deep the groove is, in pixels. deeper grooves have steeper walls
+    pub depth: f32,
+    /// how soft the edge of the groove is, as a blur sigma in pixels. 0 gives a hard vertical wall
+    pub bevel: f32,
+}
+
+impl Default for GrooveStyle {
+    fn default() -> Self {
+        GrooveStyle {
+            depth: 6.0,
+            bevel: 3.0,
+        }
+    }
+}
+
+///mirrors a point on a panel of dimensions width and height.
+///value of `symmetry` matches the enum in the randomizer. (except pillar symmetry options)
+fn get_mirrored_point (point: (f32,f32), symmetry: i32, width: f32, height: f32) -> Option<(f32,f32)> {
+    let (x,y) = point;
+
+    match symmetry {
+        0=> None,
+        1=> Some((x, height - y)), //horizontal
+        2=> Some((width -x, y)), //vertical
+        3=> Some((width -x, height - y)), //rotational 180
+        4=> Some((y, width - x)), //rotate left
+        5=> Some((height - y, x)), //rotate right
+        6=> Some((y,x)), //flip x/y
+        7=> Some((height - y, width - x)), //flip neg x/y
+        8=> Some((x, (y + (height/2.0) ) % height)), //parallel horizontal
+        9=> Some(((x + (width / 2.0)) % width  ,y)), //parallel vertical
+        10=> Some((width -x, (y + (height/2.0) ) % height)), //paralel horizontal, flipped
+        11=> Some(((x + (width / 2.0)) % width  , height -y)), //parallel vertical, flipped
+
+        // case Symmetry::PillarParallel: return Point(x + _width / 2, y);
+		// case Symmetry::PillarHorizontal: return Point(x + _width / 2, _height - 1 - y);
+		// case Symmetry::PillarVertical: return Point( _width / 2 - x, y);
+		// case Symmetry::PillarRotational: return Point(_width / 2 - x, _height - 1 - y);
+
+
+        _=>None,
+    }
+
+}
+
+///returns true if mirroring with `symmetry` flips the orientation of shapes,
+///meaning clockwise arcs become counter-clockwise.
+fn symmetry_is_reflection(symmetry: i32) -> bool {
+    matches!(symmetry, 1 | 2 | 6 | 7 | 10 | 11)
+}
+
+/// One segment of a `SpecPath`. Each segment continues from where the previous one ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PathSegment {
+    Line((f32,f32)),
+    Quad((f32,f32), (f32,f32)),
+    Cubic((f32,f32), (f32,f32), (f32,f32)),
+    /// arc around `center`, ending at the angle of `end`. radius is taken from the point the arc starts at.
+    Arc { center: (f32,f32), end: (f32,f32), clockwise: bool },
+}
+
+/// A line drawn onto a desert spec map. Coordinates are 0.0 - 1.0 across the panel.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SpecPath {
+    start: (f32,f32),
+    segments: Vec<PathSegment>,
+}
+
+impl SpecPath {
+    /// straight lines between each point, which is all the older desert functions support
+    pub(crate) fn from_polyline(points: &[(f32,f32)]) -> Result<SpecPath, Error> {
+        let Some((start, rest)) = points.split_first() else {
+            return Err(Error::NotEnoughPoints { needed: 1, given: 0 });
+        };
+        Ok(SpecPath {
+            start: *start,
+            segments: rest.iter().map(|p| PathSegment::Line(*p)).collect(),
+        })
+    }
+
+    /// builds a path from a flat point list, where each entry of `kinds` consumes the points it needs.
+    /// the first point is where the path starts.
+    pub(crate) fn from_segments(points: &[(f32,f32)], kinds: &[SegmentKind]) -> Result<SpecPath, Error> {
+        let points_needed = |kind: &SegmentKind| match kind {
+            SegmentKind::Line => 1,
+            SegmentKind::Quad => 2,
+            SegmentKind::Cubic => 3,
+            SegmentKind::ArcClockwise | SegmentKind::ArcCounterClockwise => 2,
+        };
+        let needed = 1 + kinds.iter().map(points_needed).sum::<usize>();
+        if points.len() < needed {
+            return Err(Error::NotEnoughPoints { needed, given: points.len() });
+        }
+        let mut rest = &points[1..];
+        let mut segments = Vec::new();
+        for kind in kinds {
+            let needed = points_needed(kind);
+            segments.push(match kind {
+                SegmentKind::Line => PathSegment::Line(rest[0]),
+                SegmentKind::Quad => PathSegment::Quad(rest[0], rest[1]),
+                SegmentKind::Cubic => PathSegment::Cubic(rest[0], rest[1], rest[2]),
+                SegmentKind::ArcClockwise => PathSegment::Arc { center: rest[0], end: rest[1], clockwise: true },
+                SegmentKind::ArcCounterClockwise => PathSegment::Arc { center: rest[0], end: rest[1], clockwise: false },
+            });
+            rest = &rest[needed..];
+        }
+        Ok(SpecPath { start: points[0], segments })
+    }
+
+    /// every point of the path, including control points and arc centres
+    fn points(&self) -> Vec<(f32,f32)> {
+        let mut points = vec![self.start];
+        for seg in &self.segments {
+            match *seg {
+                PathSegment::Line(p) => points.push(p),
+                PathSegment::Quad(c, p) => points.extend([c, p]),
+                PathSegment::Cubic(c1, c2, p) => points.extend([c1, c2, p]),
+                PathSegment::Arc { center, end, .. } => points.extend([center, end]),
+            }
+        }
+        points
+    }
+
+    /// applies `f` to every point of the path
+    fn map_points(&self, f: impl Fn((f32,f32)) -> (f32,f32)) -> SpecPath {
+        SpecPath {
+            start: f(self.start),
+            segments: self.segments.iter().map(|seg| match *seg {
+                PathSegment::Line(p) => PathSegment::Line(f(p)),
+                PathSegment::Quad(c, p) => PathSegment::Quad(f(c), f(p)),
+                PathSegment::Cubic(c1, c2, p) => PathSegment::Cubic(f(c1), f(c2), f(p)),
+                PathSegment::Arc { center, end, clockwise } => PathSegment::Arc { center: f(center), end: f(end), clockwise },
+            }).collect(),
+        }
+    }
+
+    /// the path mirrored on a 1.0 x 1.0 panel, or None if `symmetry` doesn't mirror anything
+    fn mirrored(&self, symmetry: i32) -> Option<SpecPath> {
+        get_mirrored_point(self.start, symmetry, 1.0, 1.0)?;
+        let mut mirrored = self.map_points(|p| get_mirrored_point(p, symmetry, 1.0, 1.0).unwrap());
+        if symmetry_is_reflection(symmetry) {
+            for seg in mirrored.segments.iter_mut() {
+                if let PathSegment::Arc { clockwise, .. } = seg {
+                    *clockwise = !*clockwise;
+                }
+            }
+        }
+        Some(mirrored)
+    }
+
+    /// adds the path to `pb`. returns the point the path ends at and the direction it is heading there.
+    fn build(&self, pb: &mut PathBuilder) -> ((f32,f32), Option<(f32,f32)>) {
+        let mut current = self.start;
+        let mut direction = None;
+        pb.move_to(current.0, current.1);
+        for seg in &self.segments {
+            let (end, from) = match *seg {
+                PathSegment::Line(p) => {
+                    pb.line_to(p.0, p.1);
+                    (p, current)
+                },
+                PathSegment::Quad(c, p) => {
+                    pb.quad_to(c.0, c.1, p.0, p.1);
+                    (p, c)
+                },
+                PathSegment::Cubic(c1, c2, p) => {
+                    pb.cubic_to(c1.0, c1.1, c2.0, c2.1, p.0, p.1);
+                    (p, c2)
+                },
+                PathSegment::Arc { center, end, clockwise } => {
+                    let radius = ((current.0 - center.0).powi(2) + (current.1 - center.1).powi(2)).sqrt();
+                    let start_angle = (current.1 - center.1).atan2(current.0 - center.0);
+                    let end_angle = (end.1 - center.1).atan2(end.0 - center.0);
+                    //y points down, so a positive sweep is clockwise on the image
+                    let tau = std::f32::consts::TAU;
+                    let mut sweep = (end_angle - start_angle).rem_euclid(tau);
+                    if sweep == 0.0 {
+                        sweep = tau; //full circle
+                    }
+                    if !clockwise {
+                        sweep -= tau;
+                    }
+                    pb.arc(center.0, center.1, radius, start_angle, sweep);
+                    let final_angle = start_angle + sweep;
+                    let arc_end = (center.0 + radius * final_angle.cos(), center.1 + radius * final_angle.sin());
+                    //tangent of the circle at the end, pointing along the direction of travel
+                    let tangent = match clockwise {
+                        true => (-final_angle.sin(), final_angle.cos()),
+                        false => (final_angle.sin(), -final_angle.cos()),
+                    };
+                    (arc_end, (arc_end.0 - tangent.0, arc_end.1 - tangent.1))
+                },
+            };
+            if end != from {
+                direction = Some((end.0 - from.0, end.1 - from.1));
+            }
+            current = end;
+        }
+        (current, direction)
+    }
+}
+
+fn path_from_points(points: &[(f32,f32)], segments: Option<&[SegmentKind]>) -> Result<SpecPath, Error> {
+    match segments {
+        Some(kinds) => SpecPath::from_segments(points, kinds),
+        None => SpecPath::from_polyline(points),
+    }
+}
+
+/// Draws a desert spec map for a line through `points`, which go from 0.0 to 1.0 across the panel.
+/// Without `segments`, the points are joined with straight lines like `generate_desert_spec_line_styled`.
+pub fn desert_spec_image(points: &[(f32,f32)], segments: Option<&[SegmentKind]>, thickness: f32, symmetry: i32, style: &LineStyle) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error> {
+    generate_desert_spec_line_img(&[path_from_points(points, segments)?], thickness, symmetry, style)
+}
+
+/// Draws the normal map to go with `desert_spec_image`. Takes the same arguments, plus the groove shape.
+pub fn desert_normal_image(points: &[(f32,f32)], segments: Option<&[SegmentKind]>, thickness: f32, symmetry: i32, style: &LineStyle, groove: &GrooveStyle) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error> {
+    generate_desert_normal_img(&[path_from_points(points, segments)?], thickness, symmetry, style, groove)
+}
+
+/// `desert_spec_image`, encoded to a wtx texture. This is what the C desert spec map functions wrap.
+pub fn desert_spec_wtx(points: &[(f32,f32)], segments: Option<&[SegmentKind]>, thickness: f32, symmetry: i32, style: &LineStyle) -> Result<Vec<u8>, Error> {
+    encode_wtx_image(desert_spec_image(points, segments, thickness, symmetry, style)?, &desert_spec_options())
+}
+
+/// `desert_normal_image`, encoded to a wtx texture. This is what the C desert normal map functions wrap.
+pub fn desert_normal_wtx(points: &[(f32,f32)], segments: Option<&[SegmentKind]>, thickness: f32, symmetry: i32, style: &LineStyle, groove: &GrooveStyle) -> Result<Vec<u8>, Error> {
+    encode_wtx_image(desert_normal_image(points, segments, thickness, symmetry, style, groove)?, &desert_normal_options())
+}
+
+pub(crate) fn desert_spec_options() -> WtxEncodeOptions {
+    WtxEncodeOptions { mipmaps: true, format: WtxFormat::DXT1, flags: WtxFlags::SRGB | WtxFlags::SPECULAR, ..Default::default() }
+}
+
+pub(crate) fn desert_normal_options() -> WtxEncodeOptions {
+    WtxEncodeOptions { mipmaps: true, format: WtxFormat::DXT1, ..Default::default() }
+}
+
+/// blurs smaller than this are skipped. they can't be seen, and `image` divides by zero on tiny ones
+const MIN_BLUR: f32 = 0.01;
+
+/// checks the things about a desert line that the drawing code can't cope with.
+/// raqote rasterizes in fixed point and overflows on huge coordinates, and huge blurs take forever,
+/// so everything is kept to a few panels' worth of pixels.
+fn check_desert_input(paths: &[SpecPath], thickness: f32, symmetry: i32, style: &LineStyle, groove: Option<&GrooveStyle>) -> Result<(), Error> {
+    let check = |name: &'static str, value: f32, range: std::ops::RangeInclusive<f32>| match range.contains(&value) {
+        true => Ok(()),
+        false => Err(Error::InvalidValue { name, value }),
+    };
+    if !(0..=11).contains(&symmetry) {
+        return Err(Error::UnknownSymmetry(symmetry));
+    }
+    for path in paths {
+        for (x, y) in path.points() {
+            check("point x", x, -8.0..=8.0)?;
+            check("point y", y, -8.0..=8.0)?;
+        }
+    }
+    check("thickness", thickness, 0.0..=512.0)?;
+    check("blur", style.blur, 0.0..=64.0)?;
+    check("start_radius", style.start_radius, 0.0..=8.0)?;
+    if let Some(groove) = groove {
+        check("depth", groove.depth, -512.0..=512.0)?;
+        check("bevel", groove.bevel, 0.0..=64.0)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn generate_desert_spec_line_img(paths : &[SpecPath], thickness : f32, symmetry : i32, style: &LineStyle) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error> {
+    check_desert_input(paths, thickness, symmetry, style, None)?;
+    let bg_img_bytes = include_bytes!("images/desertspecpanel_square_bg.png");
+    let bg_img: ImageBuffer<Rgba<u8>, Vec<u8>> = image::load_from_memory(bg_img_bytes).unwrap().to_rgba8();
+
+
+    let mut img_of_line = bg_img;
+    for path in paths {
+        img_of_line = draw_line_on_image(img_of_line, path, thickness, style);
+        if symmetry != 0 {
+            let mirrored_path = path.mirrored(symmetry).unwrap();
+            let mirrored_line_img = draw_line_on_image(img_of_line, &mirrored_path, thickness, style);
+            img_of_line = mirrored_line_img;
+        }
+    }
+    // bg_img.save("./genimg.png").unwrap(); //debug preview
+    Ok(img_of_line)
+}
+
+/// Generates a tangent-space normal map of `paths` carved as a groove into a flat panel.
+/// Only the shape settings of `style` (start circle and end cap) are used, so passing the same style as the
+/// spec map gives a groove that lines up with it.
+/// Normals use the DirectX convention: +Y points down the image.
+pub(crate) fn generate_desert_normal_img(paths : &[SpecPath], thickness : f32, symmetry : i32, style: &LineStyle, groove: &GrooveStyle) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error> {
+    check_desert_input(paths, thickness, symmetry, style, Some(groove))?;
+    let mask_style = LineStyle { r: 0xff, g: 0xff, b: 0xff, opacity: 1.0, ..*style };
+    let mut dt = DrawTarget::new(512, 512);
+    for path in paths {
+        stroke_line(&mut dt, path, thickness, &mask_style);
+        if symmetry != 0 {
+            stroke_line(&mut dt, &path.mirrored(symmetry).unwrap(), thickness, &mask_style);
+        }
+    }
+
+    //the groove is wherever the line was drawn. blur it so the walls slope instead of dropping straight down
+    let coverage = dt.get_data();
+    let mask: ImageBuffer<Luma<f32>, Vec<f32>> = ImageBuffer::from_fn(512, 512, |x, y| {
+        Luma([(coverage[(y * 512 + x) as usize] >> 24) as f32 / 255.0])
+    });
+    let mask = match groove.bevel >= MIN_BLUR {
+        true => image::imageops::blur(&mask, groove.bevel),
+        false => mask,
+    };
+    let height = |x: i64, y: i64| -> f32 {
+        let x = x.clamp(0, 511) as u32;
+        let y = y.clamp(0, 511) as u32;
+        -groove.depth * mask.get_pixel(x, y)[0]
+    };
+
+    let img = ImageBuffer::from_fn(512, 512, |x, y| {
+        let (x, y) = (x as i64, y as i64);
+        let dx = (height(x + 1, y) - height(x - 1, y)) / 2.0;
+        let dy = (height(x, y + 1) - height(x, y - 1)) / 2.0;
+        let len = (dx * dx + dy * dy + 1.0).sqrt();
+        let encode = |n: f32| ((n / len * 0.5 + 0.5) * 255.0).round() as u8;
+        Rgba([encode(-dx), encode(-dy), encode(1.0), 0xff])
+    });
+    Ok(img)
+}
+
+//draw a line with dot on an image surface.
+fn draw_line_on_image(bg_img: ImageBuffer<Rgba<u8>, Vec<u8>>, line : &SpecPath, thickness : f32, style: &LineStyle) -> ImageBuffer<Rgba<u8>, Vec<u8>>{
+
+    let mut dt = DrawTarget::new(512, 512);
+    stroke_line(&mut dt, line, thickness, style);
+
+    let mut img_of_line: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_raw(512,512,dt.get_data_u8().to_vec()).unwrap();
+    for pixel in img_of_line.pixels_mut() {
+        pixel.channels_mut().swap(0, 2); //fix pixel order
+        //raqote colors are premultiplied, image's aren't
+        let a = pixel[3] as u16;
+        if a != 0 && a != 0xff {
+            for c in pixel.channels_mut()[..3].iter_mut() {
+                *c = ((*c as u16 * 255) / a).min(255) as u8;
+            }
+        }
+    }
+    let blurred: ImageBuffer<Rgba<u8>, Vec<u8>> = match style.blur >= MIN_BLUR {
+        true => image::imageops::blur(&img_of_line, style.blur),
+        false => img_of_line,
+    };
+    let mut new_img = bg_img.clone();
+    image::imageops::overlay(&mut new_img, &blurred, 0, 0);
+    // new_img.save("./genimg_2.png").unwrap(); //debug preview
+
+    new_img
+}
+
+//strokes a line (in 0-1 panel coordinates) onto a 512x512 draw target,
+//including the start dot and end cap from `style`
+fn stroke_line(dt: &mut DrawTarget, line : &SpecPath, thickness : f32, style: &LineStyle) {
+    let mut pb = PathBuilder::new();
+
+    let scaled = line.map_points(|x| (x.0 * 512.0, x.1 * 512.0));
+    let start = scaled.start;
+
+    let (end, end_direction) = scaled.build(&mut pb);
+    let path = pb.finish();
+
+    // raqote wants premultiplied colors
+    let alpha = (style.opacity.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let premultiply = |c: u8| ((c as u16 * alpha as u16) / 255) as u8;
+    let source = Source::Solid(SolidSource {
+        r: premultiply(style.r),
+        g: premultiply(style.g),
+        b: premultiply(style.b),
+        a: alpha,
+    });
+    
+    //now prepare the dot bit
+    if style.start_radius > 0.0 {
+        pb = PathBuilder::new();
+        pb.move_to(start.0, start.1);
+        pb.line_to(start.0, start.1);
+        pb.arc(start.0, start.1, style.start_radius*thickness, 0., 360.);
+        let dotpath: Path = pb.finish();
+        dt.stroke(
+            &dotpath,
+            &source,
+            &StrokeStyle {
+                cap: LineCap::Round,
+                join: LineJoin::Round,
+                width: thickness,
+                miter_limit: 0.,
+                dash_array: vec![50., 0.],
+                dash_offset: 0.,
+            },
+            &DrawOptions::new(),
+        );
+    }
+    dt.stroke(
+        &path,
+        &source,
+        &StrokeStyle {
+            cap: match style.end_cap {
+                LineEndCap::Round => LineCap::Round,
+                LineEndCap::Nub => LineCap::Butt,
+            },
+            join: LineJoin::Round,
+            width: thickness,
+            miter_limit: 2.,
+            dash_array: vec![50., 0.],
+            dash_offset: 0.,
+        },
+        &DrawOptions::new(),
+    );
+
+    if style.end_cap == LineEndCap::Nub {
+        //continue the last segment with a thinner stub, so the end tapers like an exit
+        if let Some((dx, dy)) = end_direction {
+            let seg_len = (dx * dx + dy * dy).sqrt();
+            let nub_len = 0.5 * thickness;
+            pb = PathBuilder::new();
+            pb.move_to(end.0, end.1);
+            pb.line_to(end.0 + dx / seg_len * nub_len, end.1 + dy / seg_len * nub_len);
+            let nubpath = pb.finish();
+            dt.stroke(
+                &nubpath,
+                &source,
+                &StrokeStyle {
+                    cap: LineCap::Round,
+                    join: LineJoin::Round,
+                    width: 0.5 * thickness,
+                    miter_limit: 2.,
+                    dash_array: vec![50., 0.],
+                    dash_offset: 0.,
+                },
+                &DrawOptions::new(),
+            );
+        }
+    }
+}
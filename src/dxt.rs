@@ -0,0 +1,273 @@
+//! DXT1/DXT5 block compression, with the extra work the slower `WtxQuality` levels ask for.
+//!
+//! image_dds compresses DXT1 and DXT5 with the same fast encoder at every quality setting, so that encoder is only
+//! the starting point for `Normal` and `Slow`: the endpoints of every block are refitted to the pixels the block
+//! covers, and a block is only replaced when the new one is closer to the source. Slower levels never come out worse.
+//! Errors are measured against the palette exactly as `decode_wtx` rebuilds it.
+
+use image::{ImageBuffer, Rgba};
+
+use crate::wtx::{WtxFormat, WtxQuality};
+use crate::Error;
+
+/// Compresses `img` and the mipmaps asked for, as `WtxFormat::DXT1` or `WtxFormat::DXT5`.
+pub(crate) fn compress(img: &ImageBuffer<Rgba<u8>, Vec<u8>>, format: WtxFormat, quality: WtxQuality, mipmaps: image_dds::Mipmaps) -> Result<image_dds::Surface<Vec<u8>>, Error> {
+    let image_format = match format {
+        WtxFormat::DXT1 => image_dds::ImageFormat::BC1RgbaUnorm,
+        _ => image_dds::ImageFormat::BC3RgbaUnorm,
+    };
+    let encode_failed = |e: image_dds::error::SurfaceError| Error::EncodeFailed(e.to_string());
+    let source = image_dds::SurfaceRgba8::from_image(img);
+    if quality == WtxQuality::Fast {
+        return source.encode(image_format, image_dds::Quality::Fast, mipmaps).map_err(encode_failed);
+    }
+
+    // the uncompressed mipmaps first, so each level's blocks can be compared to its pixels
+    let levels = source.encode(image_dds::ImageFormat::Rgba8Unorm, image_dds::Quality::Fast, mipmaps).map_err(encode_failed)?;
+    let levels = image_dds::SurfaceRgba8 { width: img.width(), height: img.height(), depth: 1, layers: 1, mipmaps: levels.mipmaps, data: levels.data };
+    let mut surface = levels.encode(image_format, image_dds::Quality::Fast, image_dds::Mipmaps::FromSurface).map_err(encode_failed)?;
+    let block_bytes = match format {
+        WtxFormat::DXT1 => 8,
+        _ => 16,
+    };
+    let mut offset = 0;
+    for level in 0..surface.mipmaps {
+        let width = image_dds::mip_dimension(img.width(), level);
+        let height = image_dds::mip_dimension(img.height(), level);
+        let len = (width.div_ceil(4) * height.div_ceil(4)) as usize * block_bytes;
+        let (Some(pixels), Some(blocks)) = (levels.get(0, 0, level), surface.data.get_mut(offset..offset + len)) else {
+            return Err(Error::EncodeFailed(format!("mipmap {} is missing from the compressed data", level)));
+        };
+        refine_level(pixels, width, height, blocks, format, quality);
+        offset += len;
+    }
+    Ok(surface)
+}
+
+/// Improves the blocks of one `width` x `height` mipmap level in place
+fn refine_level(rgba: &[u8], width: u32, height: u32, blocks: &mut [u8], format: WtxFormat, quality: WtxQuality) {
+    let blocks_wide = width.div_ceil(4) as usize;
+    let block_bytes = blocks.len() / (blocks_wide * height.div_ceil(4) as usize);
+    for (i, block) in blocks.chunks_exact_mut(block_bytes).enumerate() {
+        let pixels = block_pixels(rgba, width, height, (i % blocks_wide) as u32 * 4, (i / blocks_wide) as u32 * 4);
+        match format {
+            WtxFormat::DXT1 => refine_color(&pixels, block, false, quality),
+            _ => {
+                let (alpha, color) = block.split_at_mut(8);
+                refine_alpha(&pixels, alpha, quality);
+                refine_color(&pixels, color, true, quality);
+            },
+        }
+    }
+}
+
+/// pixels of the 4x4 block at `left`, `top`, row by row. `None` for the parts of edge blocks outside the image.
+/// (not a type alias, cbindgen can't parse an `Option` of an array)
+fn block_pixels(rgba: &[u8], width: u32, height: u32, left: u32, top: u32) -> [Option<[i32; 4]>; 16] {
+    let mut pixels = [None; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let (x, y) = (left + i as u32 % 4, top + i as u32 / 4);
+        if x < width && y < height {
+            let start = (y * width + x) as usize * 4;
+            let p = &rgba[start..start + 4];
+            *pixel = Some([p[0] as i32, p[1] as i32, p[2] as i32, p[3] as i32]);
+        }
+    }
+    pixels
+}
+
+/// the 8 bit color a 565 endpoint decodes to
+fn expand_565(color: u16) -> [i32; 3] {
+    let color = color as i32;
+    [
+        (((color >> 11) & 0x1f) * 527 + 23) >> 6,
+        (((color >> 5) & 0x3f) * 259 + 33) >> 6,
+        ((color & 0x1f) * 527 + 23) >> 6,
+    ]
+}
+
+/// nearest 565 endpoint to an 8 bit color
+fn pack_565(color: [f32; 3]) -> u16 {
+    let quantize = |value: f32, max: f32| (value.clamp(0.0, 255.0) * max / 255.0).round() as u16;
+    quantize(color[0], 31.0) << 11 | quantize(color[1], 63.0) << 5 | quantize(color[2], 31.0)
+}
+
+/// The colors a block with endpoints `c0` and `c1` can use. DXT1 blocks with `c0 <= c1` only have 3,
+/// the 4th index is transparent black, which is never picked. DXT5 always has 4.
+fn color_palette(c0: u16, c1: u16, four_colors: bool) -> Vec<[i32; 3]> {
+    let (e0, e1) = (expand_565(c0), expand_565(c1));
+    let mix = |w0: i32, w1: i32, div: i32| [0, 1, 2].map(|c| (w0 * e0[c] + w1 * e1[c] + div / 2) / div);
+    match four_colors || c0 > c1 {
+        true => vec![e0, e1, mix(2, 1, 3), mix(1, 2, 3)],
+        false => vec![e0, e1, mix(1, 1, 2)],
+    }
+}
+
+/// squared error and indices of the best use of the endpoints `c0` and `c1` for `pixels`
+fn fit_color(pixels: &[Option<[i32; 4]>; 16], c0: u16, c1: u16, four_colors: bool) -> (u32, u32) {
+    let palette = color_palette(c0, c1, four_colors);
+    let (mut error, mut indices) = (0, 0);
+    for (i, pixel) in pixels.iter().enumerate() {
+        let Some(pixel) = pixel else { continue };
+        let (index, distance) = palette.iter()
+            .map(|color| (0..3).map(|c| (color[c] - pixel[c]).pow(2) as u32).sum::<u32>())
+            .enumerate()
+            .min_by_key(|&(_, distance)| distance)
+            .unwrap_or_default();
+        error += distance;
+        indices |= (index as u32) << (i * 2);
+    }
+    (error, indices)
+}
+
+/// Least squares endpoints for `pixels` if each keeps its palette entry in `indices`. `None` if they all use the same one.
+fn refit_color(pixels: &[Option<[i32; 4]>; 16], indices: u32) -> Option<(u16, u16)> {
+    let (mut aa, mut bb, mut ab) = (0.0, 0.0, 0.0);
+    let (mut ax, mut bx) = ([0.0_f32; 3], [0.0_f32; 3]);
+    for (i, pixel) in pixels.iter().enumerate() {
+        let Some(pixel) = pixel else { continue };
+        // how much of c0 is in each palette entry
+        let a = [1.0, 0.0, 2.0 / 3.0, 1.0 / 3.0][(indices >> (i * 2)) as usize & 3];
+        let b = 1.0 - a;
+        aa += a * a;
+        bb += b * b;
+        ab += a * b;
+        for c in 0..3 {
+            ax[c] += a * pixel[c] as f32;
+            bx[c] += b * pixel[c] as f32;
+        }
+    }
+    let det = aa * bb - ab * ab;
+    if det.abs() < 1e-6 {
+        return None;
+    }
+    let e0 = [0, 1, 2].map(|c| (ax[c] * bb - bx[c] * ab) / det);
+    let e1 = [0, 1, 2].map(|c| (bx[c] * aa - ax[c] * ab) / det);
+    Some((pack_565(e0), pack_565(e1)))
+}
+
+/// Refines the 8 byte color part of a block. `four_colors` is set for DXT5, where the color part never has 3 colors
+fn refine_color(pixels: &[Option<[i32; 4]>; 16], block: &mut [u8], four_colors: bool, quality: WtxQuality) {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (error, indices) = fit_color(pixels, c0, c1, four_colors);
+    let mut best = (error, indices, c0, c1);
+    let original = best;
+    // keeps a candidate if it's better. the endpoints are kept in descending order, so DXT1 blocks stay in 4 color mode
+    let consider = |best: &mut (u32, u32, u16, u16), c0: u16, c1: u16| {
+        let (c0, c1) = (c0.max(c1), c0.min(c1));
+        let (error, indices) = fit_color(pixels, c0, c1, four_colors);
+        let better = error < best.0;
+        if better {
+            *best = (error, indices, c0, c1);
+        }
+        better
+    };
+
+    let refits = match quality {
+        WtxQuality::Fast => 0,
+        WtxQuality::Normal => 2,
+        WtxQuality::Slow => 8,
+    };
+    for _ in 0..refits {
+        match refit_color(pixels, best.1) {
+            Some((c0, c1)) if consider(&mut best, c0, c1) => {},
+            _ => break,
+        }
+    }
+    if quality == WtxQuality::Slow {
+        // then try every endpoint channel one step either way, until none of them help
+        const CHANNELS: [(u16, u16); 3] = [(11, 0x1f), (5, 0x3f), (0, 0x1f)];
+        for _ in 0..32 {
+            let mut improved = false;
+            for endpoint in 0..2 {
+                for (shift, max) in CHANNELS {
+                    for step in [-1, 1] {
+                        let color = if endpoint == 0 { best.2 } else { best.3 };
+                        let value = ((color >> shift) & max) as i32 + step;
+                        if value < 0 || value > max as i32 {
+                            continue;
+                        }
+                        let nudged = (color & !(max << shift)) | (value as u16) << shift;
+                        let (c0, c1) = if endpoint == 0 { (nudged, best.3) } else { (best.2, nudged) };
+                        improved |= consider(&mut best, c0, c1);
+                    }
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+    }
+
+    if best != original {
+        block[0..2].copy_from_slice(&best.2.to_le_bytes());
+        block[2..4].copy_from_slice(&best.3.to_le_bytes());
+        block[4..8].copy_from_slice(&best.1.to_le_bytes());
+    }
+}
+
+/// the alpha values a DXT5 block with endpoints `a0` and `a1` can use
+fn alpha_palette(a0: u8, a1: u8) -> [i32; 8] {
+    let (a0, a1) = (a0 as i32, a1 as i32);
+    match a0 > a1 {
+        true => [a0, a1, (6 * a0 + a1 + 1) / 7, (5 * a0 + 2 * a1 + 1) / 7, (4 * a0 + 3 * a1 + 1) / 7,
+                 (3 * a0 + 4 * a1 + 1) / 7, (2 * a0 + 5 * a1 + 1) / 7, (a0 + 6 * a1 + 1) / 7],
+        false => [a0, a1, (4 * a0 + a1 + 1) / 5, (3 * a0 + 2 * a1 + 1) / 5, (2 * a0 + 3 * a1 + 1) / 5,
+                  (a0 + 4 * a1 + 1) / 5, 0, 255],
+    }
+}
+
+/// squared error and 48 bits of indices of the best use of the endpoints `a0` and `a1` for the alpha of `pixels`
+fn fit_alpha(pixels: &[Option<[i32; 4]>; 16], a0: u8, a1: u8) -> (u32, u64) {
+    let palette = alpha_palette(a0, a1);
+    let (mut error, mut indices) = (0, 0);
+    for (i, pixel) in pixels.iter().enumerate() {
+        let Some(pixel) = pixel else { continue };
+        let (index, distance) = palette.iter()
+            .map(|alpha| (alpha - pixel[3]).pow(2) as u32)
+            .enumerate()
+            .min_by_key(|&(_, distance)| distance)
+            .unwrap_or_default();
+        error += distance;
+        indices |= (index as u64) << (i * 3);
+    }
+    (error, indices)
+}
+
+/// Refines the 8 byte alpha part of a DXT5 block by moving its endpoints while that helps.
+/// `Normal` moves them one step at a time, `Slow` then tries again from big steps to get out of local minimums.
+fn refine_alpha(pixels: &[Option<[i32; 4]>; 16], block: &mut [u8], quality: WtxQuality) {
+    let (error, indices) = fit_alpha(pixels, block[0], block[1]);
+    let mut best = (error, indices, block[0], block[1]);
+    let original = best;
+    let steps: &[i32] = match quality {
+        WtxQuality::Fast => &[],
+        WtxQuality::Normal => &[1],
+        // what `Normal` does first, so `Slow` is never worse than it
+        WtxQuality::Slow => &[1, 16, 8, 4, 2, 1],
+    };
+    for &step in steps {
+        for _ in 0..32 {
+            let mut improved = false;
+            for (d0, d1) in [(step, 0), (-step, 0), (0, step), (0, -step)] {
+                let a0 = (best.2 as i32 + d0).clamp(0, 255) as u8;
+                let a1 = (best.3 as i32 + d1).clamp(0, 255) as u8;
+                let (error, indices) = fit_alpha(pixels, a0, a1);
+                if error < best.0 {
+                    best = (error, indices, a0, a1);
+                    improved = true;
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+    }
+
+    if best != original {
+        block[0] = best.2;
+        block[1] = best.3;
+        block[2..8].copy_from_slice(&best.1.to_le_bytes()[..6]);
+    }
+}
@@ -0,0 +1,155 @@
+//! The error type returned by the library, and the numbers C code gets for it.
+
+use std::fmt;
+
+/// Errors returned by the library.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// the image is bigger than a wtx header can describe (65535 pixels either way)
+    TooLarge { width: u32, height: u32 },
+    /// the image, or the size it was asked to be resized to, has no pixels
+    EmptyImage { width: u32, height: u32 },
+    /// the flags byte has bits set that `WtxFlags` doesn't know about
+    UnknownFlags(u8),
+    /// the data doesn't start with a wtx header
+    InvalidHeader(String),
+    /// the bytes given as an image file couldn't be read
+    InvalidImage(String),
+    /// a panel description isn't valid JSON, or doesn't match the format in `description.rs`
+    InvalidDescription(String),
+    /// a color panel grid doesn't have `width * height` values
+    InvalidGrid { width: usize, height: usize, len: usize },
+    /// a stone in a color panel grid has a color that can't be drawn
+    UnknownStoneColor(u32),
+    /// color panels can only be drawn with 9, 16 or 20 cells
+    UnsupportedStoneCount(usize),
+    /// a panel description's color panel isn't 3x3, 4x4 or 5x4 cells
+    UnsupportedPanelSize { width: usize, height: usize },
+    /// a cell of a panel description is outside its grid
+    CellOutsideGrid { x: usize, y: usize },
+    /// a desert line doesn't have enough points for its segments
+    NotEnoughPoints { needed: usize, given: usize },
+    /// symmetry isn't one of the randomizers' values from 0 to 11
+    UnknownSymmetry(i32),
+    /// a number is NaN, infinite or outside the range that can be drawn
+    InvalidValue { name: &'static str, value: f32 },
+    /// a number from C isn't one of the values of the enum it stands for
+    UnknownEnumValue { name: &'static str, value: u32 },
+    /// a pointer from C was null
+    NullPointer(&'static str),
+    /// the block compressor failed
+    EncodeFailed(String),
+    /// the texture data couldn't be decompressed
+    DecodeFailed(String),
+    /// reading or writing a file failed
+    Io(std::io::Error),
+    /// a texture pack manifest can't be read, or doesn't make sense
+    InvalidManifest(String),
+    /// one texture of a pack failed
+    PackTexture { name: String, error: Box<Error> },
+    /// an asset package isn't a zip file the reader understands, or is damaged
+    InvalidPackage(String),
+    /// an asset package has no file with this name
+    EntryNotFound(String),
+}
+
+/// Numeric version of `Error`, for C, where `wtx_tools_last_error()` returns it.
+/// The values never change, new errors get new numbers at the end.
+#[repr(u32)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum WtxErrorCode {
+    /// the last call succeeded
+    None = 0,
+    TooLarge = 1,
+    EmptyImage = 2,
+    UnknownFlags = 3,
+    InvalidHeader = 4,
+    InvalidImage = 5,
+    InvalidDescription = 6,
+    InvalidGrid = 7,
+    UnknownStoneColor = 8,
+    UnsupportedStoneCount = 9,
+    CellOutsideGrid = 10,
+    NotEnoughPoints = 11,
+    UnknownSymmetry = 12,
+    /// also used for enum values that don't exist
+    InvalidValue = 13,
+    NullPointer = 14,
+    EncodeFailed = 15,
+    DecodeFailed = 16,
+    Io = 17,
+    InvalidManifest = 18,
+    InvalidPackage = 19,
+    EntryNotFound = 20,
+    UnsupportedPanelSize = 21,
+}
+
+impl Error {
+    /// the number C code gets for this error
+    pub fn code(&self) -> WtxErrorCode {
+        match self {
+            Error::TooLarge { .. } => WtxErrorCode::TooLarge,
+            Error::EmptyImage { .. } => WtxErrorCode::EmptyImage,
+            Error::UnknownFlags(_) => WtxErrorCode::UnknownFlags,
+            Error::InvalidHeader(_) => WtxErrorCode::InvalidHeader,
+            Error::InvalidImage(_) => WtxErrorCode::InvalidImage,
+            Error::InvalidDescription(_) => WtxErrorCode::InvalidDescription,
+            Error::InvalidGrid { .. } => WtxErrorCode::InvalidGrid,
+            Error::UnknownStoneColor(_) => WtxErrorCode::UnknownStoneColor,
+            Error::UnsupportedStoneCount(_) => WtxErrorCode::UnsupportedStoneCount,
+            Error::UnsupportedPanelSize { .. } => WtxErrorCode::UnsupportedPanelSize,
+            Error::CellOutsideGrid { .. } => WtxErrorCode::CellOutsideGrid,
+            Error::NotEnoughPoints { .. } => WtxErrorCode::NotEnoughPoints,
+            Error::UnknownSymmetry(_) => WtxErrorCode::UnknownSymmetry,
+            Error::InvalidValue { .. } | Error::UnknownEnumValue { .. } => WtxErrorCode::InvalidValue,
+            Error::NullPointer(_) => WtxErrorCode::NullPointer,
+            Error::EncodeFailed(_) => WtxErrorCode::EncodeFailed,
+            Error::DecodeFailed(_) => WtxErrorCode::DecodeFailed,
+            Error::Io(_) => WtxErrorCode::Io,
+            Error::InvalidManifest(_) => WtxErrorCode::InvalidManifest,
+            Error::PackTexture { error, .. } => error.code(),
+            Error::InvalidPackage(_) => WtxErrorCode::InvalidPackage,
+            Error::EntryNotFound(_) => WtxErrorCode::EntryNotFound,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::TooLarge { width, height } => write!(f, "image is {}x{}, but wtx textures can be at most 65535x65535", width, height),
+            Error::EmptyImage { width, height } => write!(f, "image is {}x{}, which has no pixels to encode", width, height),
+            Error::UnknownFlags(bits) => write!(f, "flags {:#04x} contain unknown bits (known flags are {:#04x})", bits, crate::WtxFlags::all().bits()),
+            Error::InvalidHeader(reason) => write!(f, "not a wtx file: {}", reason),
+            Error::InvalidImage(reason) => write!(f, "could not read image: {}", reason),
+            Error::InvalidDescription(reason) => write!(f, "invalid panel description: {}", reason),
+            Error::InvalidGrid { width, height, len } => write!(f, "grid is {}x{}, but {} values were given", width, height, len),
+            Error::UnknownStoneColor(cell) => write!(f, "stone {:#x} has an unknown color", cell),
+            Error::UnsupportedStoneCount(count) => write!(f, "color panels can be 3x3, 4x4 or 5x4 cells, not {} cells", count),
+            Error::UnsupportedPanelSize { width, height } => write!(f, "color panels can be 3x3, 4x4 or 5x4 cells, not {}x{}", width, height),
+            Error::CellOutsideGrid { x, y } => write!(f, "cell ({}, {}) is outside the grid", x, y),
+            Error::NotEnoughPoints { needed, given } => write!(f, "line needs {} points, but {} were given", needed, given),
+            Error::UnknownSymmetry(symmetry) => write!(f, "unknown symmetry {}", symmetry),
+            Error::InvalidValue { name, value } => write!(f, "{} can't be {}", name, value),
+            Error::UnknownEnumValue { name, value } => write!(f, "{} {} is not one of its values", name, value),
+            Error::NullPointer(name) => write!(f, "{} is null", name),
+            Error::EncodeFailed(reason) => write!(f, "could not compress image: {}", reason),
+            Error::DecodeFailed(reason) => write!(f, "could not decompress image: {}", reason),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::InvalidManifest(reason) => write!(f, "invalid texture pack manifest: {}", reason),
+            Error::PackTexture { name, error } => write!(f, "texture {:?}: {}", name, error),
+            Error::InvalidPackage(reason) => write!(f, "could not read asset package: {}", reason),
+            Error::EntryNotFound(name) => write!(f, "asset package has no file {:?}", name),
+        }
+    }
+}
+
+// messages already include any underlying error, so there's no `source()`
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
+    }
+}
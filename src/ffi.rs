@@ -0,0 +1,447 @@
+//! The C interface, built with the `ffi` feature. Everything here is exported from the cdylib and listed in `cpp/wtx_tools.h`.
+//!
+//! These are thin wrappers around the safe functions in the other modules: they check the pointers they're given,
+//! call the Rust version, and hand the result back as a `TextureBuffer`, or an empty one if there was an error.
+//! The error is kept for `wtx_tools_last_error()` and `wtx_tools_last_error_message()`. Nothing is printed.
+
+// the extern "C" entry points take raw pointers handed to us by the host
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+use std::slice;
+use libc::{c_float, size_t};
+use crate::colorpanel::{self, ColorPanelBackground};
+use crate::description;
+use crate::desert::{self, desert_normal_wtx, desert_spec_wtx, GrooveStyle, LineEndCap, SegmentKind, SpecPath};
+use crate::wtx::{self, image_file_to_wtx, WtxEncodeOptions, WtxFlags, WtxFormat, WtxQuality};
+use crate::{Error, WtxErrorCode};
+
+/// Version of the C interface. Bumped whenever an exported function or type changes in a way that
+/// breaks code built against an older header, e.g. a function is renamed or removed or a struct changes layout.
+/// Hosts should compare it to `wtx_tools_version().abi` when loading the library.
+pub const WTX_TOOLS_ABI_VERSION: u32 = 1;
+
+/// Size of the header at the start of every wtx file. The texture data starts right after it.
+// a literal, because cbindgen leaves out constants it can't evaluate
+pub const WTX_HEADER_LEN: usize = 44;
+const _: () = assert!(WTX_HEADER_LEN == wtx::WtxHeader::LEN);
+
+#[repr(C)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+/// Version of a loaded wtx_tools library, returned by `wtx_tools_version()`.
+pub struct WtxToolsVersion {
+    /// crate version, major.minor.patch
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    /// `WTX_TOOLS_ABI_VERSION` of the library
+    pub abi: u32,
+}
+
+#[no_mangle]
+/// Returns the version of the loaded library.
+/// If `abi` isn't the `WTX_TOOLS_ABI_VERSION` from the header you built against, the library doesn't match the header
+/// and none of its other functions should be called.
+/// This function and `WtxToolsVersion` never change, so this check is always safe to make.
+pub extern "C" fn wtx_tools_version() -> WtxToolsVersion {
+    WtxToolsVersion {
+        major: env!("CARGO_PKG_VERSION_MAJOR").parse().unwrap_or(0),
+        minor: env!("CARGO_PKG_VERSION_MINOR").parse().unwrap_or(0),
+        patch: env!("CARGO_PKG_VERSION_PATCH").parse().unwrap_or(0),
+        abi: WTX_TOOLS_ABI_VERSION,
+    }
+}
+
+#[repr(C)]
+/// C-and-Rust readable struct. Contains wtx-formatted texture.
+pub struct TextureBuffer {
+    data: *mut u8,
+    len: usize,
+}
+
+#[repr(C)]
+/// C-and-Rust readable struct. Contains an image, png/jpeg/etc, to be converted to a wtx texture.
+/// image can be any format readable by rust's `image` crate.
+pub struct ImgFileBuffer {
+    data: *const c_char, //really u8 or i8. safe-ish to convert? but as c_char the C side won't complain about types
+    len: usize,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+/// Styling of the line drawn on a desert spec map, the C version of `desert::LineStyle`.
+/// `wtx_tools_default_line_style()` is the plain black line the other desert functions draw.
+pub struct LineStyle {
+    /// line color. spec maps are greyscale, so usually r == g == b, with black being no reflection
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    /// 0.0 - 1.0. lower values let more of the background specular through the line
+    pub opacity: f32,
+    /// sigma of the gaussian blur applied to the line. 0 disables blurring
+    pub blur: f32,
+    /// radius of the circle at the start of the line, as a multiple of the line thickness. 0 disables the circle
+    pub start_radius: f32,
+    /// a `LineEndCap` value. anything else is an `InvalidValue` error
+    pub end_cap: u32,
+}
+
+impl From<desert::LineStyle> for LineStyle {
+    fn from(style: desert::LineStyle) -> LineStyle {
+        LineStyle {
+            r: style.r,
+            g: style.g,
+            b: style.b,
+            opacity: style.opacity,
+            blur: style.blur,
+            start_radius: style.start_radius,
+            end_cap: style.end_cap as u32,
+        }
+    }
+}
+
+impl TryFrom<LineStyle> for desert::LineStyle {
+    type Error = Error;
+
+    fn try_from(style: LineStyle) -> Result<desert::LineStyle, Error> {
+        Ok(desert::LineStyle {
+            r: style.r,
+            g: style.g,
+            b: style.b,
+            opacity: style.opacity,
+            blur: style.blur,
+            start_radius: style.start_radius,
+            end_cap: LineEndCap::try_from(style.end_cap)?,
+        })
+    }
+}
+
+/// Generates a complete 'wtx' file from a `_grid`, with background `bg`, a `ColorPanelBackground` value
+/// The `*cost u32` in the arguments should pbe the start of a structure equivalent to  `_grid` from a `Panel`
+/// It should be flattened to a contiguous array first, so that this rust code can read it.
+/// Rust recalculates the size through the width and height. Width and height here is of the grid array - not
+/// what you would probably consider the size of the puzzle. For a 3x3 puzzle for instance, thats (3*2 +1) in each dimension on the array, so 7x7.
+#[no_mangle]
+/// If the grid can't be read, the error is kept for `wtx_tools_last_error()` and an empty TextureBuffer (null `data`, 0 `len`) is returned.
+pub extern "C" fn wtx_tools_generate_colorpanel_from_grid(grid: *const u32, width: size_t, height:size_t, bg: u32) -> TextureBuffer {
+    let result = colorpanel_from_raw(grid, width, height, bg, None);
+    texbuf_from_result(result, "could not generate color panel")
+}
+
+/// This function is intended to be called by witness randomizer code
+/// It is the same as `wtx_tools_generate_colorpanel_from_grid` but with an extra `id` argument.
+/// this will save the generated image to disk as ./generated_{id}.png, with `id` written in hex
+#[no_mangle]
+pub extern "C" fn wtx_tools_generate_colorpanel_from_grid_and_save(grid: *const u32, width: size_t, height:size_t, bg: u32, id: i32) -> TextureBuffer {
+    let result = colorpanel_from_raw(grid, width, height, bg, Some(id));
+    texbuf_from_result(result, "could not generate color panel")
+}
+
+#[no_mangle]
+/// Same as `wtx_tools_generate_colorpanel_from_grid`, but writes the texture into `out` instead of allocating it.
+/// Returns the size of the texture, which is only written if it fits in `out_capacity` bytes, so a null `out` just gets the size.
+/// `wtx_tools_encoded_size` gives the size up front. If the grid can't be read, the error is kept for `wtx_tools_last_error()` and 0 is returned.
+pub extern "C" fn wtx_tools_generate_colorpanel_from_grid_into(grid: *const u32, width: size_t, height: size_t, bg: u32, out: *mut u8, out_capacity: size_t) -> size_t {
+    let result = colorpanel_from_raw(grid, width, height, bg, None);
+    copy_into_result(result, out, out_capacity, "could not generate color panel")
+}
+
+fn colorpanel_from_raw(grid: *const u32, width: size_t, height: size_t, bg: u32, id: Option<i32>) -> Result<Vec<u8>, Error> {
+    let bg = ColorPanelBackground::try_from(bg)?;
+    colorpanel::generate_tricolor_panel_wtx(grid_from_raw(grid, width, height)?, width, height, bg, id)
+}
+
+fn grid_from_raw<'a>(grid: *const u32, width: size_t, height: size_t) -> Result<&'a [u32], Error> {
+    let Some(len) = width.checked_mul(height) else {
+        return Err(Error::InvalidGrid { width, height, len: 0 });
+    };
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if grid.is_null() {
+        return Err(Error::NullPointer("grid"));
+    }
+    Ok(unsafe { slice::from_raw_parts(grid, len) })
+}
+
+#[no_mangle]
+/// Converts ImgFileBuffer to a TextureBuffer containing an wtx-formatted image
+/// `format` and `quality` are `WtxFormat` and `WtxQuality` values.
+/// If the image can't be read, `format` or `quality` is out of range, is too large for a wtx or `flags` has unknown bits set, the error is kept for `wtx_tools_last_error()` and an empty TextureBuffer (null `data`, 0 `len`) is returned.
+pub extern "C" fn image_to_wtx(image : ImgFileBuffer, gen_mipmaps: bool, format: u32, flags: WtxFlags, quality: u32) -> TextureBuffer {
+    let result = image_to_wtx_from_raw(&image, gen_mipmaps, format, flags, quality);
+    texbuf_from_result(result, "could not convert image")
+}
+
+#[no_mangle]
+/// Same as `image_to_wtx`, but writes the texture into `out` instead of allocating it.
+/// Returns the size of the texture, which is only written if it fits in `out_capacity` bytes, so a null `out` just gets the size.
+/// `wtx_tools_encoded_size` gives the size up front. If the image can't be converted, the error is kept for `wtx_tools_last_error()` and 0 is returned.
+pub extern "C" fn image_to_wtx_into(image: ImgFileBuffer, gen_mipmaps: bool, format: u32, flags: WtxFlags, quality: u32, out: *mut u8, out_capacity: size_t) -> size_t {
+    let result = image_to_wtx_from_raw(&image, gen_mipmaps, format, flags, quality);
+    copy_into_result(result, out, out_capacity, "could not convert image")
+}
+
+fn image_to_wtx_from_raw(image: &ImgFileBuffer, gen_mipmaps: bool, format: u32, flags: WtxFlags, quality: u32) -> Result<Vec<u8>, Error> {
+    let options = WtxEncodeOptions {
+        mipmaps: gen_mipmaps,
+        format: WtxFormat::try_from(format)?,
+        flags,
+        quality: WtxQuality::try_from(quality)?,
+        ..Default::default()
+    };
+    image_file_to_wtx(image_from_raw(image)?, &options)
+}
+
+fn image_from_raw(image: &ImgFileBuffer) -> Result<&[u8], Error> {
+    if image.len == 0 {
+        return Ok(&[]);
+    }
+    if image.data.is_null() {
+        return Err(Error::NullPointer("image.data"));
+    }
+    Ok(unsafe { slice::from_raw_parts(image.data as *const u8, image.len) })
+}
+
+#[no_mangle]
+pub extern "C" fn generate_desert_spec_line_2(xpoints: *const f32, ypoints: *const f32, numpoints: size_t, xpoints2: *const f32, ypoints2: *const f32, numpoints2: size_t, thickness : c_float) -> TextureBuffer {
+    let result = (|| {
+        let points = collect_points(xpoints, ypoints, numpoints)?;
+        let points_2 = collect_points(xpoints2, ypoints2, numpoints2)?;
+        let paths = [SpecPath::from_polyline(&points)?, SpecPath::from_polyline(&points_2)?];
+        let img = desert::generate_desert_spec_line_img(&paths, thickness, 0, &desert::LineStyle::default())?;
+        wtx::encode_wtx_image(img, &desert::desert_spec_options())
+    })();
+    texbuf_from_result(result, "could not generate desert spec map")
+}
+
+#[no_mangle]
+///Generates an arbitrary spec map with a line pattern according to an array of x/y points with symmetry.
+///generated images are 512x512 squares.
+///symmetry is an `int` corresponding to the randomizers' existing Symmetry enum.
+pub extern "C" fn generate_desert_spec_line_sym(xpoints: *const f32, ypoints: *const f32, numpoints: size_t, thickness : c_float, symmetry : i32) -> TextureBuffer {
+    generate_desert_spec_line_styled(xpoints, ypoints, numpoints, thickness, symmetry, desert::LineStyle::default().into())
+}
+
+#[no_mangle]
+///Same as `generate_desert_spec_line_sym`, but the line is drawn with `style` instead of the default black line.
+///Use `wtx_tools_default_line_style()` to get a style to start from.
+///If the points, symmetry or `style.end_cap` are invalid, the error is kept for `wtx_tools_last_error()` and an empty TextureBuffer (null `data`, 0 `len`) is returned.
+pub extern "C" fn generate_desert_spec_line_styled(xpoints: *const f32, ypoints: *const f32, numpoints: size_t, thickness : c_float, symmetry : i32, style: LineStyle) -> TextureBuffer {
+    let result = collect_points(xpoints, ypoints, numpoints)
+        .and_then(|points| desert_spec_wtx(&points, None, thickness, symmetry, &style.try_into()?));
+    texbuf_from_result(result, "could not generate desert spec map")
+}
+
+#[no_mangle]
+///Generates a spec map like `generate_desert_spec_line_styled`, but the line can contain curves.
+///`segments` lists what kind of segment to draw next as `SegmentKind` values, and each one takes its points
+///in order from the x/y arrays. The first point is where the line starts, so a straight line
+///needs `numsegments + 1` points.
+pub extern "C" fn generate_desert_spec_path(xpoints: *const f32, ypoints: *const f32, numpoints: size_t, segments: *const u32, numsegments: size_t, thickness : c_float, symmetry : i32, style: LineStyle) -> TextureBuffer {
+    let result = collect_points(xpoints, ypoints, numpoints).and_then(|points| {
+        let kinds = collect_segments(segments, numsegments)?;
+        desert_spec_wtx(&points, Some(&kinds), thickness, symmetry, &style.try_into()?)
+    });
+    texbuf_from_result(result, "could not generate desert spec map")
+}
+
+#[no_mangle]
+///Same as `generate_desert_spec_path`, but writes the texture into `out` instead of allocating it.
+///The other spec map functions draw straight lines, which this does with `SegmentKind::Line` segments.
+///Returns the size of the texture, which is only written if it fits in `out_capacity` bytes, so a null `out` just gets the size.
+///`wtx_tools_encoded_size` gives the size up front. If the spec map can't be generated, the error is kept for `wtx_tools_last_error()` and 0 is returned.
+pub extern "C" fn generate_desert_spec_path_into(xpoints: *const f32, ypoints: *const f32, numpoints: size_t, segments: *const u32, numsegments: size_t, thickness : c_float, symmetry : i32, style: LineStyle, out: *mut u8, out_capacity: size_t) -> size_t {
+    let result = collect_points(xpoints, ypoints, numpoints).and_then(|points| {
+        let kinds = collect_segments(segments, numsegments)?;
+        desert_spec_wtx(&points, Some(&kinds), thickness, symmetry, &style.try_into()?)
+    });
+    copy_into_result(result, out, out_capacity, "could not generate desert spec map")
+}
+
+#[no_mangle]
+///Generates a normal map to go with a spec map from `generate_desert_spec_line_sym`, using the same points,
+///thickness and symmetry. The line is carved into the panel as a groove shaped by `groove`.
+///generated images are 512x512 squares.
+pub extern "C" fn generate_desert_normal_line_sym(xpoints: *const f32, ypoints: *const f32, numpoints: size_t, thickness : c_float, symmetry : i32, groove: GrooveStyle) -> TextureBuffer {
+    let result = collect_points(xpoints, ypoints, numpoints)
+        .and_then(|points| desert_normal_wtx(&points, None, thickness, symmetry, &desert::LineStyle::default(), &groove));
+    texbuf_from_result(result, "could not generate desert normal map")
+}
+
+#[no_mangle]
+///Generates a normal map to go with a spec map from `generate_desert_spec_path`, taking the same arguments
+///plus the shape of the groove.
+pub extern "C" fn generate_desert_normal_path(xpoints: *const f32, ypoints: *const f32, numpoints: size_t, segments: *const u32, numsegments: size_t, thickness : c_float, symmetry : i32, style: LineStyle, groove: GrooveStyle) -> TextureBuffer {
+    let result = collect_points(xpoints, ypoints, numpoints).and_then(|points| {
+        let kinds = collect_segments(segments, numsegments)?;
+        desert_normal_wtx(&points, Some(&kinds), thickness, symmetry, &style.try_into()?, &groove)
+    });
+    texbuf_from_result(result, "could not generate desert normal map")
+}
+
+#[no_mangle]
+///Same as `generate_desert_normal_path`, but writes the texture into `out` instead of allocating it.
+///Returns the size of the texture, which is only written if it fits in `out_capacity` bytes, so a null `out` just gets the size.
+///`wtx_tools_encoded_size` gives the size up front. If the normal map can't be generated, the error is kept for `wtx_tools_last_error()` and 0 is returned.
+pub extern "C" fn generate_desert_normal_path_into(xpoints: *const f32, ypoints: *const f32, numpoints: size_t, segments: *const u32, numsegments: size_t, thickness : c_float, symmetry : i32, style: LineStyle, groove: GrooveStyle, out: *mut u8, out_capacity: size_t) -> size_t {
+    let result = collect_points(xpoints, ypoints, numpoints).and_then(|points| {
+        let kinds = collect_segments(segments, numsegments)?;
+        desert_normal_wtx(&points, Some(&kinds), thickness, symmetry, &style.try_into()?, &groove)
+    });
+    copy_into_result(result, out, out_capacity, "could not generate desert normal map")
+}
+
+#[no_mangle]
+/// Returns a default groove shape for the desert normal map functions.
+pub extern "C" fn wtx_tools_default_groove_style() -> GrooveStyle {
+    GrooveStyle::default()
+}
+
+#[no_mangle]
+/// Returns the line style used by the desert functions that don't take a `LineStyle`.
+pub extern "C" fn wtx_tools_default_line_style() -> LineStyle {
+    desert::LineStyle::default().into()
+}
+
+#[no_mangle]
+///Generates an arbitrary spec map with a line pattern according to an array of x/y points.
+///generated images are 512x512 squares 
+pub extern "C" fn generate_desert_spec_line(xpoints: *const f32, ypoints: *const f32, numpoints: size_t, thickness : c_float) -> TextureBuffer {
+    generate_desert_spec_line_sym(xpoints, ypoints, numpoints, thickness, 0)
+}
+
+/// zips separate x and y arrays from C into a vec of points
+fn collect_points(xpoints: *const f32, ypoints: *const f32, numpoints: size_t) -> Result<Vec<(f32,f32)>, Error> {
+    if numpoints == 0 {
+        return Ok(Vec::new());
+    }
+    if xpoints.is_null() {
+        return Err(Error::NullPointer("xpoints"));
+    }
+    if ypoints.is_null() {
+        return Err(Error::NullPointer("ypoints"));
+    }
+    let x_vec = unsafe { slice::from_raw_parts(xpoints, numpoints) };
+    let y_vec = unsafe { slice::from_raw_parts(ypoints, numpoints) };
+    Ok(std::iter::zip(x_vec, y_vec).map(|x| (*x.0, *x.1)).collect())
+}
+
+fn collect_segments(segments: *const u32, numsegments: size_t) -> Result<Vec<SegmentKind>, Error> {
+    if numsegments == 0 {
+        return Ok(Vec::new());
+    }
+    if segments.is_null() {
+        return Err(Error::NullPointer("segments"));
+    }
+    let raw = unsafe { slice::from_raw_parts(segments, numsegments) };
+    raw.iter().map(|kind| SegmentKind::try_from(*kind)).collect()
+}
+
+thread_local! {
+    /// error of the last texture generating call on each thread, with its message
+    static LAST_ERROR: RefCell<Option<(WtxErrorCode, CString)>> = const { RefCell::new(None) };
+}
+
+/// keeps any error for `wtx_tools_last_error()`. Success clears it.
+/// nothing is printed, hosts that want to log errors get them from there
+fn record_result(result: &Result<Vec<u8>, Error>, context: &str) {
+    let last = result.as_ref().err().map(|e| {
+        let message = format!("{}: {}", context, e);
+        (e.code(), CString::new(message.replace('\0', "")).unwrap_or_default())
+    });
+    LAST_ERROR.with(|cell| *cell.borrow_mut() = last);
+}
+
+#[no_mangle]
+/// Returns why the last function on this thread that generates a texture failed, or `WtxErrorCode::None` if it succeeded.
+/// That's every function returning a `TextureBuffer`, and the `_into` ones.
+pub extern "C" fn wtx_tools_last_error() -> WtxErrorCode {
+    LAST_ERROR.with(|cell| cell.borrow().as_ref().map_or(WtxErrorCode::None, |(code, _)| *code))
+}
+
+#[no_mangle]
+/// Returns the message for `wtx_tools_last_error()`, or null if there was no error.
+/// The string belongs to the library, and stays valid until the next texture generating call on this thread.
+pub extern "C" fn wtx_tools_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|cell| cell.borrow().as_ref().map_or(std::ptr::null(), |(_, message)| message.as_ptr()))
+}
+
+/// hands a result to C through a caller's buffer, for the `_into` functions.
+/// Returns the size of the texture, which is only written if it fits. 0 means there was an error, which is recorded.
+fn copy_into_result(result: Result<Vec<u8>, Error>, out: *mut u8, out_capacity: size_t, context: &str) -> size_t {
+    record_result(&result, context);
+    match result {
+        Ok(buf) => {
+            if !out.is_null() && buf.len() <= out_capacity {
+                unsafe { std::ptr::copy_nonoverlapping(buf.as_ptr(), out, buf.len()) };
+            }
+            buf.len()
+        },
+        Err(_) => 0,
+    }
+}
+
+/// hands a result to C, recording the error and returning an empty TextureBuffer if there is one
+fn texbuf_from_result(result: Result<Vec<u8>, Error>, context: &str) -> TextureBuffer {
+    record_result(&result, context);
+    match result {
+        Ok(buf) => {
+            // as a boxed slice, so the allocation is exactly `len` long and free_texbuf can rebuild it
+            let len = buf.len();
+            let data = Box::into_raw(buf.into_boxed_slice()) as *mut u8;
+            TextureBuffer { data, len }
+        },
+        Err(_) => TextureBuffer { data: std::ptr::null_mut(), len: 0 },
+    }
+}
+
+#[no_mangle]
+/// Generates a texture from a JSON panel description. See `src/description.rs` for the format.
+/// `json` must be a null-terminated UTF-8 string.
+/// If the description can't be read or rendered, the error is kept for `wtx_tools_last_error()` and an empty TextureBuffer (null `data`, 0 `len`) is returned.
+pub extern "C" fn wtx_tools_render_description(json: *const c_char) -> TextureBuffer {
+    let result = description_from_raw(json).and_then(|desc| desc.render());
+    texbuf_from_result(result, "could not render panel description")
+}
+
+#[no_mangle]
+/// Same as `wtx_tools_render_description`, but writes the texture into `out` instead of allocating it.
+/// Returns the size of the texture, which is only written if it fits in `out_capacity` bytes, so a null `out` just gets the size.
+/// `wtx_tools_encoded_size` gives the size up front. If the description can't be read or rendered, the error is kept for `wtx_tools_last_error()` and 0 is returned.
+pub extern "C" fn wtx_tools_render_description_into(json: *const c_char, out: *mut u8, out_capacity: size_t) -> size_t {
+    let result = description_from_raw(json).and_then(|desc| desc.render());
+    copy_into_result(result, out, out_capacity, "could not render panel description")
+}
+
+fn description_from_raw(json: *const c_char) -> Result<description::PanelDescription, Error> {
+    if json.is_null() {
+        return Err(Error::NullPointer("json"));
+    }
+    let json = unsafe { std::ffi::CStr::from_ptr(json) };
+    let json = json.to_str().map_err(|e| Error::InvalidDescription(e.to_string()))?;
+    description::PanelDescription::from_json(json)
+}
+
+#[no_mangle]
+/// Returns the size in bytes of a wtx file `width` x `height` pixels big, as the `_into` functions write it.
+/// For `WtxFormat::Auto` this is the DXT5 size, which is enough for either format.
+/// The color panels are 1024x1024 DXT5 and the desert maps 512x512 DXT1, both with mipmaps.
+/// `format` is a `WtxFormat` value. Returns 0 if it isn't one, or the size can't be stored in a wtx file.
+pub extern "C" fn wtx_tools_encoded_size(width: u32, height: u32, gen_mipmaps: bool, format: u32) -> size_t {
+    WtxFormat::try_from(format).ok().and_then(|format| format.wtx_size(width, height, gen_mipmaps)).unwrap_or(0)
+}
+
+#[no_mangle]
+/// Call this to free a rust-allocated TextureBuffer
+/// Rust will keep track of memory it allocated and must be informed to free it.
+pub extern "C" fn free_texbuf(buf: TextureBuffer) {
+    if buf.data.is_null() {
+        return;
+    }
+    let s = std::ptr::slice_from_raw_parts_mut(buf.data, buf.len);
+    unsafe {
+        drop(Box::from_raw(s));
+    }
+}
@@ -0,0 +1,12 @@
+//! The hash used for texture pack indexes and the encoder's `--incremental hash`.
+
+/// FNV-1a of `parts` one after the other, as 16 hex digits.
+/// Doesn't need to be cryptographic, just stable between runs and platforms.
+pub fn fnv1a(parts: &[&[u8]]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in parts.iter().flat_map(|part| part.iter()) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
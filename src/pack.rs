@@ -0,0 +1,248 @@
+//! Texture packs: a manifest listing many textures to build at once, and the index written next to them.
+//! Needs the `pack` feature.
+//!
+//! The manifest can be TOML or JSON. Paths in it are relative to the manifest file.
+//! ```toml
+//! output_dir = "build"        # where the .wtx files go. defaults to next to the manifest
+//! index = "index.json"        # where the index goes, relative to output_dir. this is the default
+//!
+//! # encode settings for images, see `OutputOptions`
+//! [defaults]
+//! mipmaps = true
+//! flags = "SRGB"
+//!
+//! [[texture]]
+//! name = "obj_panels_color_03"    # written to build/obj_panels_color_03.wtx
+//! image = "panels/color_03.png"
+//! format = "DXT1"                 # overrides the defaults
+//!
+//! [[texture]]
+//! name = "desert/spec_01"
+//! description = "desert/spec_01.json"
+//!
+//! [[texture]]
+//! name = "obj_panels_color_04"
+//! panel = { type = "color_panel", background = "white", width = 3, height = 3 }
+//! ```
+//! Each texture has exactly one of `image`, `description` (a panel description file) or `panel` (one written inline).
+//! Descriptions use their own encode settings, with anything set on the texture itself replacing them.
+//!
+//! The index is JSON, listing every texture in manifest order:
+//! ```json
+//! { "textures": [ { "name": "obj_panels_color_03", "file": "obj_panels_color_03.wtx", "width": 1024, "height": 1024,
+//!                   "mipmaps": 11, "format": "DXT1", "flags": 1, "size": 699092, "hash": "a1b2c3d4e5f60718" } ] }
+//! ```
+
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::description::{OutputOptions, PanelDescription};
+use crate::hash::fnv1a;
+use crate::{image_file_to_wtx, Error, WtxEncodeOptions, WtxFormat, WtxHeader};
+
+/// A list of textures to build together, usually read from a file with `PackManifest::load`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PackManifest {
+    /// where the textures are written, relative to the manifest. defaults to the manifest's directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<PathBuf>,
+    /// where the index is written, relative to `output_dir`. defaults to `index.json`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<PathBuf>,
+    /// encode settings for textures made from images
+    #[serde(default)]
+    pub defaults: OutputOptions,
+    #[serde(default, rename = "texture")]
+    pub textures: Vec<PackTexture>,
+}
+
+/// One texture of a pack.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PackTexture {
+    /// name of the texture in the game. the file is `<name>.wtx` under the output directory
+    pub name: String,
+    /// image file to encode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<PathBuf>,
+    /// panel description file to render
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<PathBuf>,
+    /// panel description to render, written in the manifest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub panel: Option<PanelDescription>,
+    /// encode settings for just this texture
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
+
+/// What was built, written as the pack's index for the mod to load.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PackIndex {
+    pub textures: Vec<PackIndexEntry>,
+}
+
+/// One built texture.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PackIndexEntry {
+    pub name: String,
+    /// the .wtx file, relative to the index
+    pub file: PathBuf,
+    pub width: u16,
+    pub height: u16,
+    /// number of mipmap levels, counting the full size image
+    pub mipmaps: u16,
+    pub format: WtxFormat,
+    /// raw flags byte from the header
+    pub flags: u8,
+    /// size of the .wtx file in bytes
+    pub size: usize,
+    /// FNV-1a hash of the .wtx file as 16 hex digits, to check the file is the one that was built
+    pub hash: String,
+}
+
+impl PackManifest {
+    pub fn from_toml(manifest: &str) -> Result<PackManifest, Error> {
+        toml::from_str(manifest).map_err(|e| Error::InvalidManifest(e.to_string()))
+    }
+
+    pub fn from_json(manifest: &str) -> Result<PackManifest, Error> {
+        serde_json::from_str(manifest).map_err(|e| Error::InvalidManifest(e.to_string()))
+    }
+
+    /// Reads a manifest file, as JSON if it ends in `.json` and TOML otherwise.
+    pub fn load(path: &Path) -> Result<PackManifest, Error> {
+        let manifest = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => PackManifest::from_json(&manifest),
+            _ => PackManifest::from_toml(&manifest),
+        }
+    }
+
+    /// Checks every texture has one source and a usable name, and that no two have the same name.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut names = std::collections::HashSet::new();
+        for texture in &self.textures {
+            texture.output_file()?;
+            let sources = [texture.image.is_some(), texture.description.is_some(), texture.panel.is_some()];
+            if sources.iter().filter(|s| **s).count() != 1 {
+                return Err(Error::InvalidManifest(format!("texture {:?} needs exactly one of image, description or panel", texture.name)));
+            }
+            if !names.insert(texture.name.as_str()) {
+                return Err(Error::InvalidManifest(format!("texture {:?} is listed twice", texture.name)));
+            }
+        }
+        Ok(())
+    }
+
+    /// directory the textures go in, for a manifest in `base_dir`
+    pub fn output_dir(&self, base_dir: &Path) -> PathBuf {
+        match &self.output_dir {
+            Some(dir) => base_dir.join(dir),
+            None => base_dir.to_path_buf(),
+        }
+    }
+
+    /// path of the index, for a manifest in `base_dir`
+    pub fn index_path(&self, base_dir: &Path) -> PathBuf {
+        self.output_dir(base_dir).join(self.index.as_deref().unwrap_or(Path::new("index.json")))
+    }
+}
+
+impl PackTexture {
+    /// the .wtx file for this texture, relative to the output directory
+    pub fn output_file(&self) -> Result<PathBuf, Error> {
+        let file = PathBuf::from(format!("{}.wtx", self.name));
+        // names can have directories, but have to stay inside the output directory
+        let inside = file.components().all(|c| matches!(c, Component::Normal(_)));
+        if self.name.is_empty() || !inside {
+            return Err(Error::InvalidManifest(format!("{:?} can't be used as a texture name", self.name)));
+        }
+        Ok(file)
+    }
+
+    /// Builds this texture, reading any files relative to `base_dir`.
+    pub fn encode(&self, defaults: &OutputOptions, base_dir: &Path) -> Result<Vec<u8>, Error> {
+        self.encode_inner(defaults, base_dir).map_err(|e| Error::PackTexture { name: self.name.clone(), error: Box::new(e) })
+    }
+
+    fn encode_inner(&self, defaults: &OutputOptions, base_dir: &Path) -> Result<Vec<u8>, Error> {
+        match (&self.image, &self.description, &self.panel) {
+            (Some(image), None, None) => {
+                let options = self.output.or(*defaults).apply(WtxEncodeOptions::default());
+                image_file_to_wtx(&read_source(&base_dir.join(image))?, &options)
+            },
+            (None, Some(description), None) => {
+                let json = String::from_utf8(read_source(&base_dir.join(description))?).map_err(|e| Error::InvalidDescription(e.to_string()))?;
+                self.render(PanelDescription::from_json(&json)?)
+            },
+            (None, None, Some(panel)) => self.render(panel.clone()),
+            _ => Err(Error::InvalidManifest("needs exactly one of image, description or panel".to_string())),
+        }
+    }
+
+    fn render(&self, mut description: PanelDescription) -> Result<Vec<u8>, Error> {
+        description.output = self.output.or(description.output);
+        description.render()
+    }
+}
+
+/// Builds every texture in `manifest` and writes them and the index under its output directory.
+/// `base_dir` is where the manifest is, which paths in it are relative to.
+/// Stops at the first texture that fails, leaving the ones before it written but no index.
+pub fn build_pack(manifest: &PackManifest, base_dir: &Path) -> Result<PackIndex, Error> {
+    manifest.validate()?;
+    let output_dir = manifest.output_dir(base_dir);
+    let index_path = manifest.index_path(base_dir);
+    let index_dir = index_path.parent().unwrap_or(&output_dir);
+
+    let mut index = PackIndex::default();
+    for texture in &manifest.textures {
+        let wtx = texture.encode(&manifest.defaults, base_dir)?;
+        let path = output_dir.join(texture.output_file()?);
+        write_file(&path, &wtx)?;
+        index.textures.push(PackIndexEntry::new(texture.name.clone(), relative_to(&path, index_dir), &wtx)?);
+    }
+
+    let json = serde_json::to_string_pretty(&index).map_err(|e| Error::InvalidManifest(e.to_string()))?;
+    write_file(&index_path, json.as_bytes())?;
+    Ok(index)
+}
+
+impl PackIndexEntry {
+    fn new(name: String, file: PathBuf, wtx: &[u8]) -> Result<PackIndexEntry, Error> {
+        let header = WtxHeader::parse(wtx)?;
+        Ok(PackIndexEntry {
+            name,
+            file,
+            width: header.width,
+            height: header.height,
+            mipmaps: header.mipmaps,
+            format: header.format,
+            flags: header.flags.bits(),
+            size: wtx.len(),
+            hash: fnv1a(&[wtx]),
+        })
+    }
+}
+
+/// reads a file named in the manifest, keeping its path in the error
+fn read_source(path: &Path) -> Result<Vec<u8>, Error> {
+    std::fs::read(path).map_err(|e| Error::Io(std::io::Error::new(e.kind(), format!("{}: {}", path.display(), e))))
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(std::fs::write(path, bytes)?)
+}
+
+/// `path` relative to `dir`, written with / so the index reads the same on every platform.
+/// falls back to the whole path when it isn't under `dir`
+fn relative_to(path: &Path, dir: &Path) -> PathBuf {
+    match path.strip_prefix(dir) {
+        Ok(relative) => PathBuf::from(relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")),
+        Err(_) => path.to_path_buf(),
+    }
+}
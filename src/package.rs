@@ -0,0 +1,335 @@
+//! Reading the game's asset packages. Needs the `package` feature.
+//!
+//! The Witness keeps its assets in zip files (`data-pc.zip` and friends), with each file either stored
+//! or deflated. `AssetPackage` lists what's in one and reads files out of it, checking their CRC.
+//! Zip64 packages are supported, encrypted entries and other compression methods aren't.
+//! ```no_run
+//! let mut package = wtx_tools::package::AssetPackage::open("data-pc.zip".as_ref())?;
+//! let entry = package.find("obj_panel_color_03.wtx")?.clone();
+//! let img = wtx_tools::decode_wtx(&package.read(&entry)?)?;
+//! # Ok::<(), wtx_tools::Error>(())
+//! ```
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+
+use crate::Error;
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_DIRECTORY: u32 = 0x06054b50;
+const ZIP64_END_OF_DIRECTORY: u32 = 0x06064b50;
+const ZIP64_LOCATOR: u32 = 0x07064b50;
+const ZIP64_EXTRA: u16 = 0x0001;
+/// the end of directory record is 22 bytes plus a comment of up to 65535
+const MAX_END_SEARCH: u64 = 22 + 0xffff;
+
+/// How an entry is stored in the package.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Stored,
+    Deflate,
+    /// any other zip method, which can be listed but not read
+    Other(u16),
+}
+
+/// A file (or directory) in an asset package.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackageEntry {
+    /// path inside the package, with / between directories
+    pub name: String,
+    /// size once extracted
+    pub size: u64,
+    /// size in the package
+    pub compressed_size: u64,
+    pub compression: Compression,
+    pub crc32: u32,
+    encrypted: bool,
+    header_offset: u64,
+}
+
+impl PackageEntry {
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+
+    pub fn is_wtx(&self) -> bool {
+        self.name.to_ascii_lowercase().ends_with(".wtx")
+    }
+
+    /// `name` as a relative path to extract to, or `None` if it would end up outside the directory
+    /// it's extracted into (an absolute path, or one with `..` in it)
+    pub fn relative_path(&self) -> Option<PathBuf> {
+        let path = Path::new(&self.name);
+        let normal = path.components().all(|c| matches!(c, Component::Normal(_)));
+        (normal && !self.name.is_empty()).then(|| path.to_path_buf())
+    }
+}
+
+/// An opened asset package. The list of entries is read up front, files are only read when asked for.
+pub struct AssetPackage<R> {
+    reader: R,
+    len: u64,
+    entries: Vec<PackageEntry>,
+}
+
+impl AssetPackage<BufReader<File>> {
+    /// Opens a package file.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        AssetPackage::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> AssetPackage<R> {
+    /// Reads the list of entries from a package.
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        let (count, directory_offset) = read_end_of_directory(&mut reader, len)?;
+        if directory_offset > len {
+            return Err(Error::InvalidPackage(format!("central directory at {} is past the end of the file", directory_offset)));
+        }
+
+        reader.seek(SeekFrom::Start(directory_offset))?;
+        // every entry takes at least 46 bytes, so a damaged count can't make us reserve much more than the file
+        let mut entries = Vec::with_capacity(count.min(len / 46) as usize);
+        for _ in 0..count {
+            entries.push(read_central_header(&mut reader)?);
+        }
+        Ok(AssetPackage { reader, len, entries })
+    }
+
+    /// every entry, in the order they are listed in the package
+    pub fn entries(&self) -> &[PackageEntry] {
+        &self.entries
+    }
+
+    /// Looks up an entry by its full name.
+    pub fn find(&self, name: &str) -> Result<&PackageEntry, Error> {
+        self.entries.iter().find(|entry| entry.name == name).ok_or_else(|| Error::EntryNotFound(name.to_string()))
+    }
+
+    /// Reads and decompresses an entry, and checks it against its CRC.
+    pub fn read(&mut self, entry: &PackageEntry) -> Result<Vec<u8>, Error> {
+        let invalid = |reason: String| Error::InvalidPackage(format!("{}: {}", entry.name, reason));
+        if entry.encrypted {
+            return Err(invalid("is encrypted".to_string()));
+        }
+
+        self.reader.seek(SeekFrom::Start(entry.header_offset))?;
+        let mut header = [0; 30];
+        self.reader.read_exact(&mut header)?;
+        let mut fields = Fields::new(&header);
+        if fields.u32() != LOCAL_HEADER {
+            return Err(invalid("no file header where the directory says it is".to_string()));
+        }
+        fields.skip(22);
+        // the local header has its own name and extra field lengths, which can differ from the directory's
+        let skip = fields.u16() as i64 + fields.u16() as i64;
+        self.reader.seek(SeekFrom::Current(skip))?;
+
+        let start = self.reader.stream_position()?;
+        if entry.compressed_size > self.len.saturating_sub(start) {
+            return Err(invalid(format!("{} bytes don't fit in the package", entry.compressed_size)));
+        }
+        let mut data = vec![0; entry.compressed_size as usize];
+        self.reader.read_exact(&mut data)?;
+
+        let data = match entry.compression {
+            Compression::Stored => data,
+            Compression::Deflate => miniz_oxide::inflate::decompress_to_vec_with_limit(&data, entry.size as usize)
+                .map_err(|e| invalid(format!("could not inflate: {:?}", e.status)))?,
+            Compression::Other(method) => return Err(invalid(format!("compression method {} isn't supported", method))),
+        };
+        if data.len() as u64 != entry.size {
+            return Err(invalid(format!("is {} bytes, but should be {}", data.len(), entry.size)));
+        }
+        if crc32(&data) != entry.crc32 {
+            return Err(invalid("CRC doesn't match, the package is damaged".to_string()));
+        }
+        Ok(data)
+    }
+
+    /// Reads an entry by name.
+    pub fn read_file(&mut self, name: &str) -> Result<Vec<u8>, Error> {
+        let entry = self.find(name)?.clone();
+        self.read(&entry)
+    }
+
+    /// Extracts an entry into `dir`, keeping its path inside the package, and returns where it was written.
+    pub fn extract_to(&mut self, entry: &PackageEntry, dir: &Path) -> Result<PathBuf, Error> {
+        let relative = entry.relative_path()
+            .ok_or_else(|| Error::InvalidPackage(format!("{:?} would be extracted outside of the output directory", entry.name)))?;
+        let path = dir.join(relative);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&path)?;
+            return Ok(path);
+        }
+        let data = self.read(entry)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, data)?;
+        Ok(path)
+    }
+}
+
+/// finds the end of central directory record, and returns the number of entries and where the directory starts
+fn read_end_of_directory<R: Read + Seek>(reader: &mut R, len: u64) -> Result<(u64, u64), Error> {
+    let search = len.min(MAX_END_SEARCH);
+    reader.seek(SeekFrom::Start(len - search))?;
+    let mut tail = vec![0; search as usize];
+    reader.read_exact(&mut tail)?;
+    // searched from the back, the comment could contain the signature too
+    let end = (0..tail.len().saturating_sub(21)).rev()
+        .find(|&i| tail[i..i + 4] == END_OF_DIRECTORY.to_le_bytes())
+        .ok_or_else(|| Error::InvalidPackage("not a zip file, there's no end of central directory".to_string()))?;
+
+    let mut fields = Fields::new(&tail[end + 4..]);
+    fields.skip(6);
+    let count = fields.u16();
+    fields.skip(4);
+    let offset = fields.u32();
+    if count != 0xffff && offset != 0xffffffff {
+        return Ok((count as u64, offset as u64));
+    }
+
+    // zip64: a locator right before the record points at a bigger one
+    let end_offset = len - search + end as u64;
+    let locator_offset = end_offset.checked_sub(20).ok_or_else(|| Error::InvalidPackage("zip64 locator is missing".to_string()))?;
+    reader.seek(SeekFrom::Start(locator_offset))?;
+    let mut locator = [0; 20];
+    reader.read_exact(&mut locator)?;
+    let mut fields = Fields::new(&locator);
+    if fields.u32() != ZIP64_LOCATOR {
+        return Err(Error::InvalidPackage("zip64 locator is missing".to_string()));
+    }
+    fields.skip(4);
+    reader.seek(SeekFrom::Start(fields.u64()))?;
+    let mut record = [0; 56];
+    reader.read_exact(&mut record)?;
+    let mut fields = Fields::new(&record);
+    if fields.u32() != ZIP64_END_OF_DIRECTORY {
+        return Err(Error::InvalidPackage("zip64 end of central directory is missing".to_string()));
+    }
+    fields.skip(28);
+    let count = fields.u64();
+    fields.skip(8);
+    Ok((count, fields.u64()))
+}
+
+fn read_central_header<R: Read>(reader: &mut R) -> Result<PackageEntry, Error> {
+    let mut header = [0; 46];
+    reader.read_exact(&mut header)?;
+    let mut fields = Fields::new(&header);
+    if fields.u32() != CENTRAL_HEADER {
+        return Err(Error::InvalidPackage("central directory is damaged".to_string()));
+    }
+    fields.skip(4);
+    let flags = fields.u16();
+    let compression = match fields.u16() {
+        0 => Compression::Stored,
+        8 => Compression::Deflate,
+        method => Compression::Other(method),
+    };
+    fields.skip(4);
+    let crc32 = fields.u32();
+    let mut compressed_size = fields.u32() as u64;
+    let mut size = fields.u32() as u64;
+    let name_len = fields.u16() as usize;
+    let extra_len = fields.u16() as usize;
+    let comment_len = fields.u16() as usize;
+    fields.skip(8);
+    let mut header_offset = fields.u32() as u64;
+
+    let mut rest = vec![0; name_len + extra_len + comment_len];
+    reader.read_exact(&mut rest)?;
+    // names are UTF-8 or code page 437. the game's are plain ASCII either way
+    let name = String::from_utf8_lossy(&rest[..name_len]).into_owned();
+
+    // zip64 sizes and offset, for the fields that are maxed out, in this order
+    let mut extra = Fields::new(&rest[name_len..name_len + extra_len]);
+    while extra.remaining() >= 4 {
+        let id = extra.u16();
+        let len = extra.u16() as usize;
+        if id != ZIP64_EXTRA {
+            extra.skip(len);
+            continue;
+        }
+        let mut zip64 = Fields::new(extra.take(len));
+        for field in [&mut size, &mut compressed_size, &mut header_offset] {
+            if *field == 0xffffffff && zip64.remaining() >= 8 {
+                *field = zip64.u64();
+            }
+        }
+    }
+
+    Ok(PackageEntry { name, size, compressed_size, compression, crc32, encrypted: flags & 1 != 0, header_offset })
+}
+
+/// little endian fields out of a header. reading past the end gives zeros, callers check lengths where it matters
+struct Fields<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Fields { bytes, pos: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len().saturating_sub(self.pos)
+    }
+
+    fn skip(&mut self, n: usize) {
+        self.pos += n;
+    }
+
+    fn take(&mut self, n: usize) -> &'a [u8] {
+        let start = self.pos.min(self.bytes.len());
+        let end = (self.pos + n).min(self.bytes.len());
+        self.pos += n;
+        &self.bytes[start..end]
+    }
+
+    fn array<const N: usize>(&mut self) -> [u8; N] {
+        let mut array = [0; N];
+        let bytes = self.take(N);
+        array[..bytes.len()].copy_from_slice(bytes);
+        array
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.array())
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.array())
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.array())
+    }
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { 0xedb88320 ^ (crc >> 1) } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// the CRC-32 zip files use
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, byte| CRC_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8))
+}
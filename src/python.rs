@@ -0,0 +1,161 @@
+//! Python bindings, built with the `python` feature.
+//!
+//! Build with `maturin build --release` (see `pyproject.toml`), then `import wtx_tools`.
+//! Textures go in and out as `bytes`. Pixels are RGBA, 4 bytes per pixel, row by row, and can be passed as
+//! anything supporting the buffer protocol, so numpy arrays work both ways:
+//! ```python
+//! pixels = numpy.asarray(image, dtype=numpy.uint8)   # height x width x 4
+//! wtx = wtx_tools.generate_wtx_from_image(pixels, pixels.shape[1], pixels.shape[0], mipmaps=True)
+//! width, height, rgba = wtx_tools.decode_wtx(wtx)
+//! pixels = numpy.frombuffer(rgba, dtype=numpy.uint8).reshape(height, width, 4)
+//! ```
+//! Enum arguments are strings with the same names as in panel descriptions (`"DXT1"`, `"auto"`, `"blueprint"`, `"quad"`...),
+//! and errors are raised as `ValueError`.
+
+// pyo3's #[pyfunction] expansion converts the returned PyErr into PyErr
+#![allow(clippy::useless_conversion)]
+
+use crate::description::PanelDescription;
+use crate::{ColorPanelBackground, Error, GrooveStyle, LineStyle, SegmentKind, WtxEncodeOptions, WtxFlags, WtxFormat, WtxHeader, WtxQuality};
+use image::ImageBuffer;
+use pyo3::buffer::PyBuffer;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use serde::de::DeserializeOwned;
+
+impl From<Error> for PyErr {
+    fn from(e: Error) -> PyErr {
+        PyValueError::new_err(e.to_string())
+    }
+}
+
+/// parses an enum from its serde name, so python uses the same names as panel descriptions
+fn from_name<T: DeserializeOwned>(what: &str, name: &str) -> PyResult<T> {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .map_err(|_| PyValueError::new_err(format!("unknown {} {:?}", what, name)))
+}
+
+fn flags_from_bits(bits: u8) -> PyResult<WtxFlags> {
+    Ok(WtxFlags::from_bits_retain(bits).validate()?)
+}
+
+fn encode_options(mipmaps: bool, format: &str, flags: u8, quality: &str) -> PyResult<WtxEncodeOptions> {
+    Ok(WtxEncodeOptions {
+        mipmaps,
+        format: from_name::<WtxFormat>("format", format)?,
+        flags: flags_from_bits(flags)?,
+        quality: from_name::<WtxQuality>("quality", quality)?,
+        ..Default::default()
+    })
+}
+
+fn segments_from_names(segments: Option<Vec<String>>) -> PyResult<Option<Vec<SegmentKind>>> {
+    segments.map(|names| names.iter().map(|name| from_name("segment kind", name)).collect()).transpose()
+}
+
+/// Encodes RGBA pixels as a wtx file.
+/// `pixels` is any buffer of `width * height * 4` bytes, e.g. `bytes` or a uint8 numpy array.
+/// `flags` is a combination of `FLAG_SRGB` and `FLAG_SPECULAR`.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)] // they're keyword arguments on the python side
+#[pyo3(signature = (pixels, width, height, mipmaps=false, format="DXT5", flags=0, quality="fast"))]
+fn generate_wtx_from_image(py: Python<'_>, pixels: PyBuffer<u8>, width: u32, height: u32, mipmaps: bool, format: &str, flags: u8, quality: &str) -> PyResult<Py<PyBytes>> {
+    let options = encode_options(mipmaps, format, flags, quality)?;
+    let pixels = pixels.to_vec(py)?;
+    let len = pixels.len();
+    let img = ImageBuffer::from_raw(width, height, pixels)
+        .ok_or_else(|| PyValueError::new_err(format!("{} bytes of pixels isn't a {}x{} RGBA image", len, width, height)))?;
+    let wtx = py.allow_threads(|| crate::encode_wtx(img, &options))?;
+    Ok(PyBytes::new_bound(py, &wtx).unbind())
+}
+
+/// Converts an image file (png, jpeg, anything the `image` crate reads) to a wtx file, like the `encoder` binary does.
+#[pyfunction]
+#[pyo3(signature = (file, mipmaps=false, format="DXT5", flags=0, quality="fast"))]
+fn image_file_to_wtx(py: Python<'_>, file: PyBuffer<u8>, mipmaps: bool, format: &str, flags: u8, quality: &str) -> PyResult<Py<PyBytes>> {
+    let options = encode_options(mipmaps, format, flags, quality)?;
+    let file = file.to_vec(py)?;
+    let wtx = py.allow_threads(|| crate::image_file_to_wtx(&file, &options))?;
+    Ok(PyBytes::new_bound(py, &wtx).unbind())
+}
+
+/// Decodes the full size image of a wtx file. Returns `(width, height, pixels)`, with pixels as RGBA bytes.
+#[pyfunction]
+fn decode_wtx(py: Python<'_>, wtx: PyBuffer<u8>) -> PyResult<(u32, u32, Py<PyBytes>)> {
+    let wtx = wtx.to_vec(py)?;
+    let img = py.allow_threads(|| crate::decode_wtx(&wtx))?;
+    Ok((img.width(), img.height(), PyBytes::new_bound(py, img.as_raw()).unbind()))
+}
+
+/// Reads the header of a wtx file into a dict.
+/// `flags` is the raw flags byte, `format` is "DXT1" or "DXT5" and `average` is the (r, g, b, a) average color.
+#[pyfunction]
+fn read_header(py: Python<'_>, wtx: PyBuffer<u8>) -> PyResult<Py<PyDict>> {
+    let header = WtxHeader::parse(&wtx.to_vec(py)?)?;
+    let dict = PyDict::new_bound(py);
+    dict.set_item("width", header.width)?;
+    dict.set_item("height", header.height)?;
+    dict.set_item("mipmaps", header.mipmaps)?;
+    dict.set_item("flags", header.flags.bits())?;
+    dict.set_item("average", (header.average[0], header.average[1], header.average[2], header.average[3]))?;
+    dict.set_item("format", format!("{:?}", header.format))?;
+    dict.set_item("data_len", header.data_len)?;
+    Ok(dict.unbind())
+}
+
+/// Generates a color panel wtx from a flattened puzzle grid, like `wtx_tools_generate_colorpanel_from_grid` does from C.
+/// `grid` holds `width * height` ints, column by column.
+#[pyfunction]
+#[pyo3(signature = (grid, width, height, background="blueprint"))]
+fn colorpanel_from_grid(py: Python<'_>, grid: Vec<u32>, width: usize, height: usize, background: &str) -> PyResult<Py<PyBytes>> {
+    let background = from_name::<ColorPanelBackground>("background", background)?;
+    let wtx = py.allow_threads(|| crate::colorpanel_from_grid(&grid, width, height, background))?;
+    Ok(PyBytes::new_bound(py, &wtx).unbind())
+}
+
+/// Generates a desert spec map wtx for a line through `points`, a list of (x, y) pairs from 0.0 to 1.0.
+/// `segments` optionally lists segment kinds ("line", "quad", "cubic", "arc_clockwise", "arc_counter_clockwise")
+/// to draw curves, see `SegmentKind`. For a styled line use `render_description`.
+#[pyfunction]
+#[pyo3(signature = (points, thickness, symmetry=0, segments=None))]
+fn desert_spec_wtx(py: Python<'_>, points: Vec<(f32, f32)>, thickness: f32, symmetry: i32, segments: Option<Vec<String>>) -> PyResult<Py<PyBytes>> {
+    let segments = segments_from_names(segments)?;
+    let wtx = py.allow_threads(|| crate::desert_spec_wtx(&points, segments.as_deref(), thickness, symmetry, &LineStyle::default()))?;
+    Ok(PyBytes::new_bound(py, &wtx).unbind())
+}
+
+/// Generates the normal map to go with `desert_spec_wtx`, taking the same arguments plus the groove shape.
+#[pyfunction]
+#[pyo3(signature = (points, thickness, symmetry=0, segments=None, depth=GrooveStyle::default().depth, bevel=GrooveStyle::default().bevel))]
+fn desert_normal_wtx(py: Python<'_>, points: Vec<(f32, f32)>, thickness: f32, symmetry: i32, segments: Option<Vec<String>>, depth: f32, bevel: f32) -> PyResult<Py<PyBytes>> {
+    let segments = segments_from_names(segments)?;
+    let groove = GrooveStyle { depth, bevel };
+    let wtx = py.allow_threads(|| crate::desert_normal_wtx(&points, segments.as_deref(), thickness, symmetry, &LineStyle::default(), &groove))?;
+    Ok(PyBytes::new_bound(py, &wtx).unbind())
+}
+
+/// Renders a JSON panel description (see `src/description.rs`) to a wtx file.
+#[pyfunction]
+fn render_description(py: Python<'_>, json: &str) -> PyResult<Py<PyBytes>> {
+    let description = PanelDescription::from_json(json)?;
+    let wtx = py.allow_threads(|| description.render())?;
+    Ok(PyBytes::new_bound(py, &wtx).unbind())
+}
+
+#[pymodule]
+fn wtx_tools(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add("FLAG_SRGB", WtxFlags::SRGB.bits())?;
+    m.add("FLAG_SPECULAR", WtxFlags::SPECULAR.bits())?;
+    m.add("HEADER_LEN", WtxHeader::LEN)?;
+    m.add_function(wrap_pyfunction!(generate_wtx_from_image, m)?)?;
+    m.add_function(wrap_pyfunction!(image_file_to_wtx, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_wtx, m)?)?;
+    m.add_function(wrap_pyfunction!(read_header, m)?)?;
+    m.add_function(wrap_pyfunction!(colorpanel_from_grid, m)?)?;
+    m.add_function(wrap_pyfunction!(desert_spec_wtx, m)?)?;
+    m.add_function(wrap_pyfunction!(desert_normal_wtx, m)?)?;
+    m.add_function(wrap_pyfunction!(render_description, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,541 @@
+//! Reading and writing wtx textures.
+
+use image::{ImageBuffer, Rgba};
+use serde::{Deserialize, Serialize};
+use crate::dxt;
+use crate::Error;
+
+#[repr(u32)]
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum WtxFormat {
+    DXT5,
+    DXT1,
+    /// pick DXT1 or DXT5 based on the image's alpha channel. see `WtxFormat::for_image`
+    #[serde(rename = "auto")]
+    Auto,
+}
+
+impl TryFrom<u32> for WtxFormat {
+    type Error = Error;
+
+    /// for values from C, which can be anything
+    fn try_from(value: u32) -> Result<WtxFormat, Error> {
+        match value {
+            0 => Ok(WtxFormat::DXT5),
+            1 => Ok(WtxFormat::DXT1),
+            2 => Ok(WtxFormat::Auto),
+            _ => Err(Error::UnknownEnumValue { name: "format", value }),
+        }
+    }
+}
+
+/// What an image's alpha channel contains
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum AlphaContent {
+    /// every pixel is fully opaque
+    Opaque,
+    /// every pixel is either fully opaque or fully transparent
+    OneBit,
+    /// anything else
+    Gradient,
+}
+
+impl AlphaContent {
+    pub fn of_image(img: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> AlphaContent {
+        let mut content = AlphaContent::Opaque;
+        for p in img.pixels() {
+            match p[3] {
+                0xff => {},
+                0x00 => content = AlphaContent::OneBit,
+                _ => return AlphaContent::Gradient,
+            }
+        }
+        content
+    }
+}
+
+impl WtxFormat {
+    /// Resolves `Auto` to the format to use for `img`, leaving other formats alone.
+    /// Opaque images get DXT1, since it is half the size. Anything with transparency gets DXT5:
+    /// DXT1 could store 1-bit alpha, but the compressor we use drops alpha entirely for DXT1.
+    pub fn for_image(self, img: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> WtxFormat {
+        match self {
+            WtxFormat::Auto => match AlphaContent::of_image(img) {
+                AlphaContent::Opaque => WtxFormat::DXT1,
+                AlphaContent::OneBit | AlphaContent::Gradient => WtxFormat::DXT5,
+            },
+            format => format,
+        }
+    }
+
+    /// Size in bytes of a wtx file in this format, header included, as `encode_wtx` would write it.
+    /// `Auto` gives the DXT5 size, which is the larger of the two.
+    /// `None` if the size doesn't fit in a wtx header.
+    pub fn wtx_size(self, width: u32, height: u32, mipmaps: bool) -> Option<usize> {
+        if width == 0 || height == 0 || width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return None;
+        }
+        let block_bytes = match self {
+            WtxFormat::DXT1 => 8,
+            WtxFormat::DXT5 | WtxFormat::Auto => 16,
+        };
+        // mipmaps go all the way down to 1x1, and every level takes at least one 4x4 block
+        let levels = match mipmaps {
+            true => mip_levels(width, height),
+            false => 1,
+        };
+        let blocks: usize = (0..levels)
+            .map(|level| ((width >> level).max(1).div_ceil(4) * (height >> level).max(1).div_ceil(4)) as usize)
+            .sum();
+        Some(WtxHeader::LEN + blocks * block_bytes)
+    }
+}
+
+/// number of mipmap levels from `width` x `height` down to 1x1, counting the full size
+fn mip_levels(width: u32, height: u32) -> u32 {
+    32 - width.max(height).leading_zeros()
+}
+
+#[repr(u32)]
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How much effort the block compressor puts into encoding.
+/// `Fast` is fine for textures generated at runtime, slower settings give fewer artifacts on hand-made textures.
+pub enum WtxQuality {
+    /// the blocks of image_dds' compressor as they are
+    Fast,
+    /// refits the endpoints of every block to its pixels
+    Normal,
+    /// also searches around the endpoints. a lot slower than `Normal`
+    Slow,
+}
+
+impl TryFrom<u32> for WtxQuality {
+    type Error = Error;
+
+    /// for values from C, which can be anything
+    fn try_from(value: u32) -> Result<WtxQuality, Error> {
+        match value {
+            0 => Ok(WtxQuality::Fast),
+            1 => Ok(WtxQuality::Normal),
+            2 => Ok(WtxQuality::Slow),
+            _ => Err(Error::UnknownEnumValue { name: "quality", value }),
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// Flags stored in header byte 20 of a wtx file.
+    /// Nothing documents these, so the names come from which of the game's textures have them set.
+    /// Mipmaps don't have a flag, their count has its own header field.
+    #[repr(C)]
+    #[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+    pub struct WtxFlags: u8 {
+        /// set on color textures (the color bunker panels, desert spec maps) and not on normal maps,
+        /// so most likely the texture is sampled as sRGB
+        const SRGB = 0x01;
+        /// set on the desert panels' specular maps
+        const SPECULAR = 0x04;
+    }
+}
+
+impl Serialize for WtxFlags {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        bitflags::serde::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for WtxFlags {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        bitflags::serde::deserialize(deserializer)
+    }
+}
+
+impl WtxFlags {
+    /// Checks that only named flags are set, as anything else may mean something to the game we don't know about.
+    pub fn validate(self) -> Result<WtxFlags, Error> {
+        match WtxFlags::from_bits(self.bits()) {
+            Some(flags) => Ok(flags),
+            None => Err(Error::UnknownFlags(self.bits())),
+        }
+    }
+}
+
+impl std::fmt::Display for WtxFlags {
+    /// prints the raw byte and the names of the flags in it, e.g. `0x05 (SRGB | SPECULAR)`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#04x}", self.bits())?;
+        if !self.is_empty() {
+            write!(f, " (")?;
+            bitflags::parser::to_writer(self, &mut *f)?;
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// Size the image should be rounded up to before encoding
+#[derive(PartialEq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeRounding {
+    /// keep the size as it is
+    #[default]
+    None,
+    /// next power of two in each direction, e.g. 600x300 becomes 1024x512
+    PowerOfTwo,
+    /// next multiple of 4 in each direction, so the image is made of whole DXT blocks
+    MultipleOf4,
+}
+
+/// How to make an image fit a bigger size
+#[derive(PartialEq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeAdjust {
+    /// stretch the image to fill the new size
+    #[default]
+    Scale,
+    /// keep the image as it is in the top left corner and fill the rest with transparent black
+    Pad,
+}
+
+/// Filter used when scaling images. Same as `image::imageops::FilterType`
+#[derive(PartialEq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResizeFilter {
+    Nearest,
+    Triangle,
+    #[default]
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+impl From<ResizeFilter> for image::imageops::FilterType {
+    fn from(filter: ResizeFilter) -> Self {
+        match filter {
+            ResizeFilter::Nearest => image::imageops::FilterType::Nearest,
+            ResizeFilter::Triangle => image::imageops::FilterType::Triangle,
+            ResizeFilter::CatmullRom => image::imageops::FilterType::CatmullRom,
+            ResizeFilter::Gaussian => image::imageops::FilterType::Gaussian,
+            ResizeFilter::Lanczos3 => image::imageops::FilterType::Lanczos3,
+        }
+    }
+}
+
+/// Everything `encode_wtx` can be told about how to encode an image.
+/// The defaults match the `encoder` binary with no options.
+#[derive(Clone, Copy, Debug)]
+pub struct WtxEncodeOptions {
+    pub mipmaps: bool,
+    pub format: WtxFormat,
+    pub flags: WtxFlags,
+    pub quality: WtxQuality,
+    /// scale the image to exactly this size first
+    pub resize: Option<(u32, u32)>,
+    /// then round the size up
+    pub rounding: SizeRounding,
+    /// how the image is made to fit the rounded size
+    pub adjust: SizeAdjust,
+    /// filter for any scaling
+    pub filter: ResizeFilter,
+    /// how the average color in the header is worked out
+    pub average: AverageMode,
+    /// store the real average alpha in the header, instead of 1.0
+    pub average_alpha: bool,
+    /// store exactly this RGBA color in the header instead of computing one
+    pub average_override: Option<[f32; 4]>,
+}
+
+impl Default for WtxEncodeOptions {
+    fn default() -> Self {
+        WtxEncodeOptions {
+            mipmaps: false,
+            format: WtxFormat::DXT5,
+            flags: WtxFlags::empty(),
+            quality: WtxQuality::Fast,
+            resize: None,
+            rounding: SizeRounding::None,
+            adjust: SizeAdjust::Scale,
+            filter: ResizeFilter::CatmullRom,
+            average: AverageMode::SrgbMean,
+            average_alpha: false,
+            average_override: None,
+        }
+    }
+}
+
+/// How the average color stored in the header is computed.
+/// The game seems to use this color for distant or not yet loaded textures.
+#[derive(PartialEq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AverageMode {
+    /// plain mean of the 8 bit channel values
+    #[default]
+    SrgbMean,
+    /// converts to linear light before averaging. the result is stored as linear values
+    LinearMean,
+    /// mean of the 8 bit values weighted by alpha, so transparent pixels don't count.
+    /// fully transparent images fall back to `SrgbMean`
+    AlphaWeighted,
+}
+
+fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Average color of an image for the wtx header. alpha is 1.0 unless `alpha` is set
+fn average_color(img: &ImageBuffer<Rgba<u8>, Vec<u8>>, mode: AverageMode, alpha: bool) -> [f32; 4] {
+    let mut sums = [0.0_f64; 4];
+    let mut weight = 0.0_f64;
+    for p in img.pixels() {
+        let a = p[3] as f64 / 255.;
+        let w = match mode {
+            AverageMode::AlphaWeighted => a,
+            _ => 1.0,
+        };
+        for c in 0..3 {
+            let value = p[c] as f64 / 255.;
+            sums[c] += w * match mode {
+                AverageMode::LinearMean => srgb_to_linear(value),
+                _ => value,
+            };
+        }
+        sums[3] += a;
+        weight += w;
+    }
+    if mode == AverageMode::AlphaWeighted && weight == 0.0 {
+        return average_color(img, AverageMode::SrgbMean, alpha);
+    }
+    let count = img.pixels().len() as f64;
+    [
+        (sums[0] / weight) as f32,
+        (sums[1] / weight) as f32,
+        (sums[2] / weight) as f32,
+        //hardcoded b/c i dont want rounding errors
+        if alpha { (sums[3] / count) as f32 } else { 1.0 },
+    ]
+}
+
+/// Safe version of `image_to_wtx`, for use from Rust. `file` is an image file in any format the `image` crate can read.
+pub fn image_file_to_wtx(file: &[u8], options: &WtxEncodeOptions) -> Result<Vec<u8>, Error> {
+    let img = image::load_from_memory(file).map_err(|e| Error::InvalidImage(e.to_string()))?.to_rgba8();
+    encode_wtx(img, options)
+}
+
+/// Encodes an image as a wtx file, as-is. Short for `encode_wtx` with just these options set.
+pub fn generate_wtx_from_image(img: ImageBuffer<Rgba<u8>, Vec<u8>>, gen_mipmaps: bool, format: WtxFormat, flags: WtxFlags, quality: WtxQuality) -> Result<Vec<u8>, Error> {
+    encode_wtx_image(img, &WtxEncodeOptions { mipmaps: gen_mipmaps, format, flags, quality, ..Default::default() })
+}
+
+/// Encodes an image as a wtx file, resizing it first as `options` ask.
+pub fn encode_wtx(img: ImageBuffer<Rgba<u8>, Vec<u8>>, options: &WtxEncodeOptions) -> Result<Vec<u8>, Error> {
+    let img = fit_image(img, options)?;
+    encode_wtx_image(img, options)
+}
+
+/// Applies the resize and rounding settings of `options`.
+/// The final size is worked out and checked first, so an impossible size is an error instead of a huge allocation
+fn fit_image(img: ImageBuffer<Rgba<u8>, Vec<u8>>, options: &WtxEncodeOptions) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Err(Error::EmptyImage { width, height });
+    }
+    let (width, height) = options.resize.unwrap_or((width, height));
+    if width == 0 || height == 0 {
+        return Err(Error::EmptyImage { width, height });
+    }
+    let round = |n: u32| match options.rounding {
+        SizeRounding::None => Some(n),
+        SizeRounding::PowerOfTwo => n.checked_next_power_of_two(),
+        SizeRounding::MultipleOf4 => n.checked_add(3).map(|n| n / 4 * 4),
+    };
+    let (Some(rounded_width), Some(rounded_height)) = (round(width), round(height)) else {
+        return Err(Error::TooLarge { width, height });
+    };
+    if rounded_width > u16::MAX as u32 || rounded_height > u16::MAX as u32 {
+        return Err(Error::TooLarge { width: rounded_width, height: rounded_height });
+    }
+
+    let img = match (width, height) == img.dimensions() {
+        true => img,
+        false => image::imageops::resize(&img, width, height, options.filter.into()),
+    };
+    if (rounded_width, rounded_height) == img.dimensions() {
+        return Ok(img);
+    }
+    Ok(match options.adjust {
+        SizeAdjust::Scale => image::imageops::resize(&img, rounded_width, rounded_height, options.filter.into()),
+        SizeAdjust::Pad => {
+            let mut padded = ImageBuffer::new(rounded_width, rounded_height);
+            image::imageops::replace(&mut padded, &img, 0, 0);
+            padded
+        },
+    })
+}
+
+/// Encodes an image as-is. only the size checks are done, resizing is `encode_wtx`'s job
+pub(crate) fn encode_wtx_image(mut img: ImageBuffer<Rgba<u8>, Vec<u8>>, options: &WtxEncodeOptions) -> Result<Vec<u8>, Error> {
+    let flags = options.flags.validate()?;
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Err(Error::EmptyImage { width, height });
+    }
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(Error::TooLarge { width, height });
+    }
+    let format = options.format.for_image(&img);
+    image::imageops::flip_vertical_in_place(&mut img);
+    let average = match options.average_override {
+        Some(color) => color,
+        None => average_color(&img, options.average, options.average_alpha),
+    };
+
+    let mipmaps = match options.mipmaps {
+        true => image_dds::Mipmaps::GeneratedAutomatic,
+        false => image_dds::Mipmaps::GeneratedExact(1),
+    };
+    let surface = dxt::compress(&img, format, options.quality, mipmaps)?;
+    
+    
+    //Create the WTX header
+    let mut wtx_data = vec![
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x12, 0x00, //header always constant
+        0x00, 0x00, 0x00, 0x00, //these 4 = length of rest of data
+        0x00, 0x00, //width 
+        0x00, 0x00, //height
+        0x01, 0x00, //"depth" always (0x01, 0x00) as these are 2d images
+        0x00, 0x00, //number of mipmaps 
+        flags.bits(), //see WtxFlags
+        0x00, 0x00, 0x00, //these 3 always zero (part of bitmask but always 0?)
+        0x00,0x00,0x00,0x00, //float R
+        0x00,0x00,0x00,0x00, //float G
+        0x00,0x00,0x00,0x00, //float B
+        0x00,0x00,0x00,0x00, //float A
+        0x00,0x00,0x00,0x00, //image format
+    ];
+    wtx_data.splice(8..12, (surface.data.len() as u32 + 32_u32).to_le_bytes().to_vec());
+    wtx_data.splice(12..14, (img.width() as u16).to_le_bytes().to_vec());
+    wtx_data.splice(14..16, (img.height() as u16).to_le_bytes().to_vec());
+    wtx_data.splice(18..20, (surface.mipmaps as u16).to_le_bytes().to_vec());
+    
+    for (i, channel) in average.iter().enumerate() {
+        wtx_data.splice(24 + i * 4..28 + i * 4, channel.to_le_bytes());
+    }
+    let format_id = match format {
+        WtxFormat::DXT5 => vec![0x44, 0x58, 0x54, 0x35,],
+        WtxFormat::DXT1 => vec![0x44, 0x58, 0x54, 0x31,],
+        WtxFormat::Auto => unreachable!(),
+    };
+    wtx_data.splice(40..44, format_id);
+    
+    // there is only one layer, so that's all of the data. get_data(0) works its size out again and comes up
+    // short for some small non-square mipmapped images, which left the length in the header wrong
+    wtx_data.extend_from_slice(&surface.data);
+    Ok(wtx_data)
+}
+
+/// The fields of a wtx header, as read back from a file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WtxHeader {
+    pub width: u16,
+    pub height: u16,
+    /// number of mipmap levels, counting the full size image
+    pub mipmaps: u16,
+    /// kept as stored, so may contain bits without a name
+    pub flags: WtxFlags,
+    /// average color stored in the header
+    pub average: [f32; 4],
+    /// `DXT1` or `DXT5`
+    pub format: WtxFormat,
+    /// length of the texture data after the header
+    pub data_len: usize,
+}
+
+impl WtxHeader {
+    /// size of the header in bytes
+    pub const LEN: usize = 44;
+
+    /// Reads the header at the start of a wtx file.
+    pub fn parse(wtx: &[u8]) -> Result<WtxHeader, Error> {
+        if wtx.len() < WtxHeader::LEN {
+            return Err(Error::InvalidHeader(format!("file is {} bytes, too short for a header", wtx.len())));
+        }
+        if wtx[0..8] != [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x12, 0x00] {
+            return Err(Error::InvalidHeader(String::from("file doesn't start with the wtx magic bytes")));
+        }
+        let u16_at = |i: usize| u16::from_le_bytes([wtx[i], wtx[i + 1]]);
+        let f32_at = |i: usize| f32::from_le_bytes([wtx[i], wtx[i + 1], wtx[i + 2], wtx[i + 3]]);
+        let format = match &wtx[40..44] {
+            b"DXT1" => WtxFormat::DXT1,
+            b"DXT5" => WtxFormat::DXT5,
+            other => return Err(Error::InvalidHeader(format!("unknown texture format {:?}", String::from_utf8_lossy(other)))),
+        };
+        // the stored length counts the 32 header bytes after it
+        let Some(data_len) = u32::from_le_bytes([wtx[8], wtx[9], wtx[10], wtx[11]]).checked_sub(32) else {
+            return Err(Error::InvalidHeader(String::from("stored length is shorter than the header")));
+        };
+        let (width, height, depth, mipmaps) = (u16_at(12), u16_at(14), u16_at(16), u16_at(18));
+        if width == 0 || height == 0 {
+            return Err(Error::InvalidHeader(format!("texture is {}x{}", width, height)));
+        }
+        // only 2d textures are known, which always have a depth of 1
+        if depth != 1 {
+            return Err(Error::InvalidHeader(format!("depth is {}, not 1", depth)));
+        }
+        let max_mipmaps = mip_levels(width as u32, height as u32);
+        if mipmaps == 0 || mipmaps as u32 > max_mipmaps {
+            return Err(Error::InvalidHeader(format!("{} mipmaps, but a {}x{} texture has 1 to {}", mipmaps, width, height, max_mipmaps)));
+        }
+        Ok(WtxHeader {
+            width,
+            height,
+            mipmaps,
+            flags: WtxFlags::from_bits_retain(wtx[20]),
+            average: [f32_at(24), f32_at(28), f32_at(32), f32_at(36)],
+            format,
+            data_len: data_len as usize,
+        })
+    }
+}
+
+/// Decodes the full size image of a wtx file, the opposite of `encode_wtx`.
+pub fn decode_wtx(wtx: &[u8]) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error> {
+    let header = WtxHeader::parse(wtx)?;
+    let data = &wtx[WtxHeader::LEN..];
+    if data.len() < header.data_len {
+        return Err(Error::InvalidHeader(format!("header says there are {} bytes of data, but the file only has {}", header.data_len, data.len())));
+    }
+    let surface = image_dds::Surface {
+        width: header.width as u32,
+        height: header.height as u32,
+        depth: 1,
+        layers: 1,
+        mipmaps: header.mipmaps as u32,
+        image_format: match header.format {
+            WtxFormat::DXT5 => image_dds::ImageFormat::BC3RgbaUnorm,
+            _ => image_dds::ImageFormat::BC1RgbaUnorm,
+        },
+        data: &data[..header.data_len],
+    };
+    let mut img = surface.decode_rgba8()
+        .map_err(|e| Error::DecodeFailed(e.to_string()))?
+        .to_image(0)
+        .map_err(|e| Error::DecodeFailed(e.to_string()))?;
+    image::imageops::flip_vertical_in_place(&mut img);
+    Ok(img)
+}
+
+impl std::fmt::Display for WtxHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "size: {}x{}", self.width, self.height)?;
+        writeln!(f, "format: {:?}", self.format)?;
+        writeln!(f, "mipmaps: {}", self.mipmaps)?;
+        writeln!(f, "flags: {}", self.flags)?;
+        writeln!(f, "average color: {:.3} {:.3} {:.3} {:.3}", self.average[0], self.average[1], self.average[2], self.average[3])?;
+        write!(f, "data: {} bytes", self.data_len)
+    }
+}
@@ -0,0 +1,149 @@
+//! Checks the C API from the outside.
+//!
+//! `cpp/wtx_tools.h` and `cpp/wtx_tools_c.h` are regenerated with cbindgen and compared against the committed ones.
+//! Then `cpp/test.cpp` and `cpp/test.c` are compiled against them and the cdylib and run. The C++ one calls every
+//! exported function, and the textures both write are decoded here. If no compiler is found (`c++` and `cc`, or
+//! whatever `CXX` and `CC` are set to), that part is skipped.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use wtx_tools::WtxHeader;
+
+/// cbindgen config and the header it generates
+const HEADERS: [(&str, &str); 2] = [("cbindgen.toml", "wtx_tools.h"), ("cbindgen_c.toml", "wtx_tools_c.h")];
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// the directory the cdylib is in. `cargo test` builds it next to the test binary in `deps`,
+/// the copy in the directory above is only updated by `cargo build`
+fn library_dir() -> PathBuf {
+    std::env::current_exe().unwrap().parent().unwrap().to_path_buf()
+}
+
+fn generate_headers(out_dir: &Path) {
+    for (config, header) in HEADERS {
+        let config = cbindgen::Config::from_file(manifest_dir().join(config)).unwrap();
+        let bindings = cbindgen::generate_with_config(manifest_dir(), config).unwrap();
+        bindings.write_to_file(out_dir.join(header));
+    }
+}
+
+/// Compiles `cpp/<source>` against fresh headers and the library, and runs it with `args` in a new directory.
+/// Returns that directory and what the program printed, or `None` if the compiler isn't there.
+fn build_and_run(compiler_var: &str, default_compiler: &str, source: &str, flags: &[&str], args: &[&Path]) -> Option<(PathBuf, String)> {
+    let compiler = std::env::var(compiler_var).unwrap_or_else(|_| default_compiler.to_string());
+    if Command::new(&compiler).arg("--version").output().is_err() {
+        eprintln!("skipping {}, no compiler found (tried `{}`)", source, compiler);
+        return None;
+    }
+
+    let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("c_api_{}", source.replace('.', "_")));
+    let _ = std::fs::remove_dir_all(&out_dir);
+    std::fs::create_dir_all(&out_dir).unwrap();
+    generate_headers(&out_dir);
+
+    let lib_dir = library_dir();
+    let library = lib_dir.join(format!("{}wtx_tools{}", std::env::consts::DLL_PREFIX, std::env::consts::DLL_SUFFIX));
+    assert!(library.exists(), "{:?} wasn't built", library);
+
+    let program = out_dir.join("test.out");
+    let status = Command::new(&compiler)
+        .arg(manifest_dir().join("cpp").join(source))
+        .args(flags)
+        .args(["-Wall", "-Werror", "-o"])
+        .arg(&program)
+        .arg("-I").arg(&out_dir)
+        .arg("-L").arg(&lib_dir)
+        .arg("-lwtx_tools")
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .status()
+        .unwrap();
+    assert!(status.success(), "compiling cpp/{} failed", source);
+
+    let output = Command::new(&program)
+        .args(args)
+        .current_dir(&out_dir)
+        // cargo points this at target/debug as well, which would load the stale copy of the library over the rpath
+        .env_remove("LD_LIBRARY_PATH")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "cpp/{} failed\nstdout:\n{}\nstderr:\n{}", source, stdout, stderr);
+    Some((out_dir, stdout.into_owned()))
+}
+
+/// both programs print the version of the library they loaded
+fn check_version(stdout: &str) {
+    let expected = format!("version: {}, abi {}", env!("CARGO_PKG_VERSION"), wtx_tools::WTX_TOOLS_ABI_VERSION);
+    assert!(stdout.contains(&expected), "expected {:?} in the output:\n{}", expected, stdout);
+}
+
+/// decodes each `<name>.wtx` and checks it is a `size` x `size` square
+fn check_textures(dir: &Path, expected: &[(&str, u16)]) {
+    for &(name, size) in expected {
+        let wtx = std::fs::read(dir.join(name).with_extension("wtx")).unwrap();
+        let header = WtxHeader::parse(&wtx).unwrap();
+        assert_eq!((header.width, header.height), (size, size), "size of {}", name);
+        assert_eq!(wtx.len(), WtxHeader::LEN + header.data_len, "length of {}", name);
+        wtx_tools::decode_wtx(&wtx).unwrap();
+    }
+}
+
+#[test]
+fn headers_are_up_to_date() {
+    let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("c_api_headers");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    generate_headers(&out_dir);
+
+    for (config, header) in HEADERS {
+        let generated = std::fs::read_to_string(out_dir.join(header)).unwrap();
+        let committed = std::fs::read_to_string(manifest_dir().join("cpp").join(header)).unwrap();
+        assert!(
+            generated == committed,
+            "cpp/{} is out of date, regenerate it with `cbindgen --config {} --crate wtx_tools --output cpp/{}`", header, config, header,
+        );
+    }
+}
+
+#[test]
+fn cpp_test_program() {
+    let image = manifest_dir().join("tests/goldens/encode_gradient.png");
+    let Some((out_dir, stdout)) = build_and_run("CXX", "c++", "test.cpp", &["-std=c++17"], &[&image]) else {
+        return;
+    };
+    check_version(&stdout);
+
+    let expected = [
+        ("colorpanel", 1024),
+        ("colorpanel_saved", 1024),
+        ("description", 1024),
+        ("desert_spec_line", 512),
+        ("desert_spec_line_2", 512),
+        ("desert_spec_line_sym", 512),
+        ("desert_spec_line_styled", 512),
+        ("desert_spec_path", 512),
+        ("desert_normal_line_sym", 512),
+        ("desert_normal_path", 512),
+    ];
+    check_textures(&out_dir, &expected);
+
+    let source = image::open(image).unwrap();
+    for name in ["image", "image_auto"] {
+        let wtx = std::fs::read(out_dir.join(name).with_extension("wtx")).unwrap();
+        let decoded = wtx_tools::decode_wtx(&wtx).unwrap();
+        assert_eq!(decoded.dimensions(), (source.width(), source.height()), "size of {}", name);
+    }
+    assert!(out_dir.join("generated_41.png").exists());
+}
+
+#[test]
+fn c_test_program() {
+    let Some((out_dir, stdout)) = build_and_run("CC", "cc", "test.c", &["-std=c99", "-Wextra", "-pedantic"], &[]) else {
+        return;
+    };
+    check_version(&stdout);
+    check_textures(&out_dir, &[("c_desert_spec_path", 512), ("c_desert_normal_path", 512), ("c_description", 1024)]);
+}
@@ -0,0 +1,257 @@
+//! Runs the `encoder` binary on small directories of images.
+
+use image::{ImageBuffer, Rgba};
+use std::path::{Path, PathBuf};
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+/// an empty directory for a test, under the target directory
+fn test_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_png(path: &Path, shade: u8) {
+    ImageBuffer::from_fn(16, 16, |x, y| Rgba([shade, (x * 16) as u8, (y * 16) as u8, 255])).save(path).unwrap();
+}
+
+fn encoder(args: &[&str], dir: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_encoder")).args(args).current_dir(dir).output().unwrap()
+}
+
+const PANEL: &str = r#"{ "type": "color_panel", "background": "white", "width": 3, "height": 3, "cells": [] }"#;
+
+#[test]
+fn incremental_hash_runs_twice_in_place() {
+    let dir = test_dir("encoder_incremental_hash");
+    write_png(&dir.join("a.png"), 0);
+    write_png(&dir.join("b.png"), 128);
+
+    // the cache is written into the input directory, and mustn't be picked up as a texture on the next run
+    let first = encoder(&["--input-dir", ".", "--incremental", "hash"], &dir);
+    assert!(first.status.success(), "{}", String::from_utf8_lossy(&first.stderr));
+    assert!(dir.join(".encoder-hashes.json").exists());
+
+    let second = encoder(&["--input-dir", ".", "--incremental", "hash"], &dir);
+    let stderr = String::from_utf8_lossy(&second.stderr);
+    assert!(second.status.success(), "{}", stderr);
+    assert!(stderr.contains("2 were skipped"), "{}", stderr);
+
+    write_png(&dir.join("b.png"), 255);
+    let third = encoder(&["--input-dir", ".", "--incremental", "hash"], &dir);
+    let stderr = String::from_utf8_lossy(&third.stderr);
+    assert!(third.status.success(), "{}", stderr);
+    assert!(stderr.contains("1 were skipped"), "{}", stderr);
+
+    // the settings are part of the hash
+    let fourth = encoder(&["--input-dir", ".", "--incremental", "hash", "--format", "DXT1"], &dir);
+    let stderr = String::from_utf8_lossy(&fourth.stderr);
+    assert!(fourth.status.success(), "{}", stderr);
+    assert!(stderr.contains("0 were skipped"), "{}", stderr);
+}
+
+#[test]
+fn outputs_must_not_collide() {
+    let dir = test_dir("encoder_collisions");
+    write_png(&dir.join("a.png"), 0);
+    std::fs::write(dir.join("a.json"), PANEL).unwrap();
+    let output = encoder(&["--input-dir", "."], &dir);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("would both be written to"), "{}", stderr);
+    assert!(!dir.join("a.wtx").exists(), "nothing should be encoded");
+
+    // a template without the stem gives every file the same name
+    std::fs::remove_file(dir.join("a.json")).unwrap();
+    write_png(&dir.join("b.png"), 128);
+    let output = encoder(&["--input-dir", ".", "--name-template", "texture.wtx"], &dir);
+    assert!(!output.status.success());
+    assert!(!dir.join("texture.wtx").exists());
+
+    let output = encoder(&["--input-dir", "."], &dir);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn parallel_jobs() {
+    let dir = test_dir("encoder_jobs");
+    write_png(&dir.join("a.png"), 0);
+    write_png(&dir.join("b.png"), 128);
+    std::fs::create_dir(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("a.json"), PANEL).unwrap();
+
+    // the same output written differently is still the same output
+    for second in ["./a.json", "sub/../a.json"] {
+        let output = encoder(&["--jobs", "2", "a.png", second], &dir);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "{}", second);
+        assert!(stderr.contains("would both be written to"), "{}", stderr);
+        assert!(!dir.join("a.wtx").exists(), "nothing should be encoded");
+    }
+
+    let output = encoder(&["--jobs", "2", "a.png", "b.png", "sub/../a.json", "--name-template", "{name}.wtx"], &dir);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("finished processing 3 files"), "{}", stderr);
+    for name in ["a.png.wtx", "b.png.wtx", "a.json.wtx"] {
+        wtx_tools::WtxHeader::parse(&std::fs::read(dir.join(name)).unwrap()).unwrap();
+    }
+}
+
+#[test]
+fn oversized_resizes_are_errors() {
+    let dir = test_dir("encoder_oversized");
+    write_png(&dir.join("a.png"), 0);
+    // each of these would need gigabytes if the image was resized before the size was checked
+    for args in [&["--resize", "70000x70000"][..], &["--resize", "40000x40000", "--round-size", "pow2"], &["--resize", "3000000000x1", "--round-size", "pow2"]] {
+        let output = encoder(&[&["a.png"], args].concat(), &dir);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success());
+        assert!(stderr.contains("wtx textures can be at most 65535x65535"), "{:?}: {}", args, stderr);
+    }
+    let output = encoder(&["a.png", "--resize", "0x16"], &dir);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("has no pixels"));
+}
+
+#[test]
+fn output_paths() {
+    let dir = test_dir("encoder_outputs");
+    write_png(&dir.join("a.png"), 0);
+
+    let output = encoder(&["a.png", "-o", "out/custom.wtx"], &dir);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let wtx = std::fs::read(dir.join("out/custom.wtx")).unwrap();
+    assert!(!dir.join("a.wtx").exists());
+
+    let output = encoder(&["a.png", "-o", "-"], &dir);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert_eq!(output.stdout, wtx);
+    assert!(!stderr.contains("Saved"), "{}", stderr);
+    assert!(!dir.join("-").exists());
+
+    let output = encoder(&["a.png", "--name-template", "{stem}_{ext}.wtx"], &dir);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(std::fs::read(dir.join("a_png.wtx")).unwrap(), wtx);
+}
+
+#[test]
+fn no_clobber_and_force() {
+    let dir = test_dir("encoder_overwrite");
+    write_png(&dir.join("a.png"), 0);
+    std::fs::write(dir.join("a.wtx"), "old").unwrap();
+
+    let output = encoder(&["a.png", "--no-clobber"], &dir);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("already exists"), "{}", stderr);
+    assert_eq!(std::fs::read(dir.join("a.wtx")).unwrap(), b"old");
+
+    // without either, existing outputs are replaced
+    let output = encoder(&["a.png"], &dir);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let wtx = std::fs::read(dir.join("a.wtx")).unwrap();
+    assert_ne!(wtx, b"old");
+
+    // the output is newer than the input, so --incremental skips it unless --force is given
+    std::fs::write(dir.join("a.wtx"), "old").unwrap();
+    let output = encoder(&["a.png", "--incremental", "mtime"], &dir);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 were skipped"), "{}", stderr);
+    assert_eq!(std::fs::read(dir.join("a.wtx")).unwrap(), b"old");
+
+    let output = encoder(&["a.png", "--incremental", "mtime", "--force"], &dir);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(std::fs::read(dir.join("a.wtx")).unwrap(), wtx);
+}
+
+#[test]
+fn render_writes_wtx_and_preview() {
+    let dir = test_dir("encoder_render");
+    std::fs::write(dir.join("panel.json"), PANEL).unwrap();
+
+    let output = encoder(&["render", "panel.json"], &dir);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let wtx = std::fs::read(dir.join("panel.wtx")).unwrap();
+    let texture = wtx_tools::decode_wtx(&wtx).unwrap();
+    let preview = image::open(dir.join("panel.png")).unwrap();
+    assert!(preview.width() > 0 && preview.height() > 0);
+    assert_eq!(texture.dimensions(), wtx_tools::description::PanelDescription::from_json(PANEL).unwrap().render_image().unwrap().dimensions());
+
+    let output = encoder(&["render", "panel.json", "-o", "-"], &dir);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(output.stdout, wtx);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_encoder"))
+        .args(["render", "-o", "stdin.wtx", "--no-preview"])
+        .current_dir(&dir)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(PANEL.as_bytes()).unwrap();
+    assert!(child.wait().unwrap().success());
+    assert_eq!(std::fs::read(dir.join("stdin.wtx")).unwrap(), wtx);
+    assert!(!dir.join("stdin.png").exists());
+}
+
+#[test]
+fn render_from_stdin_needs_output() {
+    let dir = test_dir("encoder_render_stdin");
+    // stdin is kept open, so this only finishes if the encoder fails before reading it
+    let mut child = Command::new(env!("CARGO_BIN_EXE_encoder"))
+        .arg("render")
+        .current_dir(&dir)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdin = child.stdin.take();
+    let start = Instant::now();
+    while child.try_wait().unwrap().is_none() {
+        if start.elapsed() > Duration::from_secs(30) {
+            child.kill().unwrap();
+            panic!("the encoder waited for stdin");
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("--output is required"), "{}", stderr);
+}
+
+#[test]
+fn flags_and_info() {
+    let dir = test_dir("encoder_info");
+    write_png(&dir.join("a.png"), 0);
+    write_png(&dir.join("b.png"), 128);
+
+    let output = encoder(&["a.png", "--flag", "srgb", "--flag", "specular", "--format", "DXT1"], &dir);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let header = wtx_tools::WtxHeader::parse(&std::fs::read(dir.join("a.wtx")).unwrap()).unwrap();
+    assert_eq!(header.flags, wtx_tools::WtxFlags::SRGB | wtx_tools::WtxFlags::SPECULAR);
+    let output = encoder(&["b.png", "--include-mipmaps"], &dir);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let output = encoder(&["info", "a.wtx"], &dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(stdout.trim_end(), header.to_string());
+    assert!(stdout.contains("size: 16x16") && stdout.contains("format: DXT1"), "{}", stdout);
+
+    // several files are listed one after another, each under its name
+    let output = encoder(&["info", "a.wtx", "b.wtx"], &dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.starts_with("a.wtx:\n"), "{}", stdout);
+    assert!(stdout.contains("\n\nb.wtx:\n") && stdout.contains("mipmaps: 5"), "{}", stdout);
+
+    let output = encoder(&["info", "a.png"], &dir);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not a wtx file"));
+}
@@ -0,0 +1,223 @@
+//! Golden file tests for the wtx output.
+//!
+//! Most cases render and encode a fixed input, then compares the header byte for byte and the decoded pixels
+//! (downscaled, within a small tolerance) against the files in `tests/goldens`.
+//! After an intended change to drawing or encoding, regenerate them with
+//! `WTX_UPDATE_GOLDENS=1 cargo test --test golden` and check the new pngs before committing them.
+//! The others compare the quality levels, and check that unsupported panel sizes and damaged headers are errors.
+
+use image::{ImageBuffer, Rgba};
+use std::path::PathBuf;
+use wtx_tools::description::PanelDescription;
+use wtx_tools::{Error, WtxEncodeOptions, WtxErrorCode, WtxFormat, WtxHeader, WtxQuality};
+
+/// largest difference allowed in any channel of the downscaled pixels
+const TOLERANCE: u8 = 4;
+/// goldens are stored at most this big, to keep the repository small
+const GOLDEN_SIZE: u32 = 128;
+
+fn golden_path(name: &str, extension: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/goldens").join(name).with_extension(extension)
+}
+
+fn header_hex(wtx: &[u8]) -> String {
+    let bytes: Vec<String> = wtx[..WtxHeader::LEN].iter().map(|b| format!("{:02x}", b)).collect();
+    // one line per 4 bytes, so a diff points at the field that changed
+    bytes.chunks(4).map(|line| line.join(" ")).collect::<Vec<_>>().join("\n") + "\n"
+}
+
+fn thumbnail(img: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let scale = (GOLDEN_SIZE as f32 / img.width().max(img.height()) as f32).min(1.0);
+    let width = ((img.width() as f32 * scale).round() as u32).max(1);
+    let height = ((img.height() as f32 * scale).round() as u32).max(1);
+    image::imageops::resize(img, width, height, image::imageops::FilterType::Triangle)
+}
+
+fn check_golden(name: &str, wtx: &[u8]) {
+    let header = header_hex(wtx);
+    let pixels = thumbnail(&wtx_tools::decode_wtx(wtx).unwrap());
+    let header_path = golden_path(name, "header");
+    let pixels_path = golden_path(name, "png");
+
+    if std::env::var_os("WTX_UPDATE_GOLDENS").is_some() {
+        std::fs::create_dir_all(header_path.parent().unwrap()).unwrap();
+        std::fs::write(&header_path, header).unwrap();
+        pixels.save(&pixels_path).unwrap();
+        return;
+    }
+
+    let expected_header = std::fs::read_to_string(&header_path)
+        .unwrap_or_else(|_| panic!("{:?} is missing, run with WTX_UPDATE_GOLDENS=1 to create it", header_path));
+    assert_eq!(header, expected_header, "header of {} changed", name);
+
+    let expected = image::open(&pixels_path).unwrap().to_rgba8();
+    assert_eq!(pixels.dimensions(), expected.dimensions(), "size of {} changed", name);
+    let worst = pixels.pixels().zip(expected.pixels()).enumerate()
+        .map(|(i, (a, b))| (i, a.0.iter().zip(b.0.iter()).map(|(x, y)| x.abs_diff(*y)).max().unwrap()))
+        .max_by_key(|(_, diff)| *diff)
+        .unwrap();
+    assert!(
+        worst.1 <= TOLERANCE,
+        "pixels of {} changed: off by {} at ({}, {})", name, worst.1, worst.0 as u32 % pixels.width(), worst.0 as u32 / pixels.width(),
+    );
+}
+
+fn check_description(name: &str, json: &str) {
+    let description = PanelDescription::from_json(json).unwrap();
+    check_golden(name, &description.render().unwrap());
+}
+
+fn check_color_panel(name: &str, background: &str, width: usize, height: usize, cells: &str) {
+    check_description(name, &format!(
+        r#"{{ "type": "color_panel", "background": "{}", "width": {}, "height": {}, "cells": [{}] }}"#,
+        background, width, height, cells,
+    ));
+}
+
+#[test]
+fn color_panel_blueprint() {
+    check_color_panel("color_panel_blueprint", "blueprint", 3, 3, r#"
+        { "x": 0, "y": 0, "decoration": "stone", "color": "tricolor_white" },
+        { "x": 1, "y": 1, "decoration": "stone", "color": "tricolor_purple" },
+        { "x": 2, "y": 2, "decoration": "stone", "color": "tricolor_green" }
+    "#);
+}
+
+#[test]
+fn color_panel_white() {
+    check_color_panel("color_panel_white", "white", 4, 4, r#"
+        { "x": 0, "y": 0, "decoration": "stone", "color": "tricolor_new_white" },
+        { "x": 3, "y": 0, "decoration": "stone", "color": "tricolor_new_pink" },
+        { "x": 0, "y": 3, "decoration": "stone", "color": "tricolor_new_blue" },
+        { "x": 3, "y": 3, "decoration": "stone", "color": "tricolor_new_yellow" }
+    "#);
+}
+
+#[test]
+fn color_panel_light_grey() {
+    check_color_panel("color_panel_light_grey", "light_grey", 5, 4, r#"
+        { "x": 0, "y": 1, "decoration": "stone", "color": "tricolor_green" },
+        { "x": 2, "y": 1, "decoration": "stone", "color": "tricolor_purple" },
+        { "x": 4, "y": 2, "decoration": "stone", "color": "tricolor_white" }
+    "#);
+}
+
+#[test]
+fn color_panel_dark_grey() {
+    check_color_panel("color_panel_dark_grey", "dark_grey", 3, 3, r#"
+        { "x": 1, "y": 0, "decoration": "stone", "color": "tricolor_new_pink" },
+        { "x": 1, "y": 2, "decoration": "stone", "color": "tricolor_new_blue" }
+    "#);
+}
+
+#[test]
+fn color_panel_elevator() {
+    check_color_panel("color_panel_elevator", "elevator", 4, 4, "");
+}
+
+#[test]
+fn color_panel_sizes_are_checked() {
+    // same cell counts as the supported sizes, but laid out differently
+    for (width, height) in [(4, 5), (2, 10), (10, 2), (1, 9), (8, 2)] {
+        let json = format!(r#"{{ "type": "color_panel", "background": "white", "width": {}, "height": {}, "cells": [] }}"#, width, height);
+        let result = PanelDescription::from_json(&json).unwrap().render();
+        assert!(matches!(result, Err(Error::UnsupportedPanelSize { width: w, height: h }) if (w, h) == (width, height)), "{}x{}", width, height);
+        assert_eq!(result.unwrap_err().code(), WtxErrorCode::UnsupportedPanelSize);
+    }
+}
+
+#[test]
+fn desert_spec_polyline() {
+    check_description("desert_spec_polyline", r#"{
+        "type": "desert_spec",
+        "thickness": 30.0,
+        "symmetry": "vertical",
+        "lines": [{ "points": [[0.1, 0.9], [0.1, 0.5], [0.4, 0.5], [0.4, 0.1]] }]
+    }"#);
+}
+
+#[test]
+fn desert_spec_curves() {
+    check_description("desert_spec_curves", r#"{
+        "type": "desert_spec",
+        "thickness": 24.0,
+        "symmetry": "rotational",
+        "style": { "r": 40, "g": 40, "b": 40, "end_cap": "nub" },
+        "lines": [{
+            "points": [[0.1, 0.5], [0.2, 0.2], [0.35, 0.35], [0.45, 0.4], [0.5, 0.2], [0.6, 0.3], [0.3, 0.3], [0.3, 0.1]],
+            "segments": ["quad", "cubic", "arc_clockwise"]
+        }]
+    }"#);
+}
+
+#[test]
+fn desert_normal() {
+    check_description("desert_normal", r#"{
+        "type": "desert_normal",
+        "thickness": 30.0,
+        "symmetry": "horizontal",
+        "lines": [{ "points": [[0.2, 0.2], [0.8, 0.2], [0.8, 0.45]] }]
+    }"#);
+}
+
+#[test]
+fn encode_gradient() {
+    // odd sized, with alpha, so padding, auto format and the alpha average are all covered
+    let img = ImageBuffer::from_fn(30, 21, |x, y| Rgba([(x * 8) as u8, (y * 12) as u8, 128, if x < 15 { 255 } else { 96 }]));
+    let options = WtxEncodeOptions {
+        mipmaps: true,
+        format: WtxFormat::Auto,
+        rounding: wtx_tools::SizeRounding::PowerOfTwo,
+        adjust: wtx_tools::SizeAdjust::Pad,
+        average_alpha: true,
+        ..Default::default()
+    };
+    check_golden("encode_gradient", &wtx_tools::encode_wtx(img, &options).unwrap());
+}
+
+/// a 64x32 texture with all 7 mipmap levels, with one u16 header field changed
+fn wtx_with_field(offset: usize, value: u16) -> Vec<u8> {
+    let img = ImageBuffer::from_fn(64, 32, |x, y| Rgba([(x * 4) as u8, (y * 8) as u8, 128, 255]));
+    let mut wtx = wtx_tools::encode_wtx(img, &WtxEncodeOptions { mipmaps: true, ..Default::default() }).unwrap();
+    wtx[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    wtx
+}
+
+#[test]
+fn damaged_headers_are_errors() {
+    // mipmap count, depth, height and width. 0 mipmaps used to panic inside the decoder
+    for (offset, value) in [(18, 0), (18, 8), (18, 0xffff), (16, 0), (16, 2), (12, 0), (14, 0)] {
+        let wtx = wtx_with_field(offset, value);
+        assert!(matches!(WtxHeader::parse(&wtx), Err(Error::InvalidHeader(_))), "{} at byte {}", value, offset);
+        assert!(matches!(wtx_tools::decode_wtx(&wtx), Err(Error::InvalidHeader(_))), "{} at byte {}", value, offset);
+    }
+    assert_eq!(wtx_tools::decode_wtx(&wtx_with_field(18, 1)).unwrap().dimensions(), (64, 32));
+}
+
+/// summed squared difference of the decoded texture from `img`, in RGB and optionally alpha
+fn encode_error(img: &ImageBuffer<Rgba<u8>, Vec<u8>>, format: WtxFormat, quality: WtxQuality) -> u64 {
+    let options = WtxEncodeOptions { format, quality, mipmaps: true, ..Default::default() };
+    let decoded = wtx_tools::decode_wtx(&wtx_tools::encode_wtx(img.clone(), &options).unwrap()).unwrap();
+    let channels = if format == WtxFormat::DXT5 { 4 } else { 3 };
+    img.pixels().zip(decoded.pixels())
+        .map(|(a, b)| (0..channels).map(|c| (a[c] as i64 - b[c] as i64).pow(2) as u64).sum::<u64>())
+        .sum()
+}
+
+#[test]
+fn slower_qualities_are_closer_to_the_source() {
+    // noisy colors and alpha at an odd size, so no block is easy and the edge blocks are only partly used
+    let mut seed = 0x2545_f491_u32;
+    let img = ImageBuffer::from_fn(37, 29, |x, y| {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        let [r, g, b, a] = seed.to_le_bytes();
+        Rgba([r / 2 + (x * 3) as u8, g / 4 + (y * 4) as u8, b, a / 2 + 128])
+    });
+    for format in [WtxFormat::DXT1, WtxFormat::DXT5] {
+        let [fast, normal, slow] = [WtxQuality::Fast, WtxQuality::Normal, WtxQuality::Slow].map(|quality| encode_error(&img, format, quality));
+        assert!(slow <= normal && normal < fast, "{:?}: fast {}, normal {}, slow {}", format, fast, normal, slow);
+        assert!(slow < fast, "{:?}: fast {}, slow {}", format, fast, slow);
+    }
+}
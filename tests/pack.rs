@@ -0,0 +1,127 @@
+//! Builds small texture packs from TOML and JSON manifests, and checks the index and the errors for broken ones.
+
+use image::{ImageBuffer, Rgba};
+use std::path::{Path, PathBuf};
+use wtx_tools::hash::fnv1a;
+use wtx_tools::pack::{build_pack, PackIndex, PackManifest};
+use wtx_tools::{Error, WtxFormat, WtxHeader};
+
+/// an empty directory for a test, with a 32x32 image in it at `images/a.png`
+fn test_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("images")).unwrap();
+    ImageBuffer::from_fn(32, 32, |x, y| Rgba([(x * 8) as u8, (y * 8) as u8, 64, 255])).save(dir.join("images/a.png")).unwrap();
+    dir
+}
+
+fn header(path: &Path) -> WtxHeader {
+    WtxHeader::parse(&std::fs::read(path).unwrap()).unwrap()
+}
+
+#[test]
+fn builds_toml_manifest() {
+    let dir = test_dir("pack_toml");
+    std::fs::write(dir.join("panel.json"), r#"{ "type": "color_panel", "background": "white", "width": 3, "height": 3, "cells": [] }"#).unwrap();
+    let manifest = PackManifest::from_toml(r#"
+        output_dir = "build"
+
+        [defaults]
+        mipmaps = true
+        flags = "SPECULAR"
+
+        [[texture]]
+        name = "panels/color_03"
+        image = "images/a.png"
+
+        [[texture]]
+        name = "plain"
+        image = "images/a.png"
+        format = "DXT1"
+        mipmaps = false
+
+        [[texture]]
+        name = "desert/panel"
+        description = "panel.json"
+        mipmaps = false
+    "#).unwrap();
+    let index = build_pack(&manifest, &dir).unwrap();
+
+    let written: PackIndex = serde_json::from_str(&std::fs::read_to_string(dir.join("build/index.json")).unwrap()).unwrap();
+    assert_eq!(written, index);
+    let names: Vec<&str> = index.textures.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, ["panels/color_03", "plain", "desert/panel"]);
+    for entry in &index.textures {
+        let wtx = std::fs::read(dir.join("build").join(&entry.file)).unwrap();
+        assert_eq!(entry.file, PathBuf::from(format!("{}.wtx", entry.name)));
+        assert_eq!((entry.size, entry.hash.as_str()), (wtx.len(), fnv1a(&[&wtx]).as_str()));
+    }
+
+    // images get the defaults, with the texture's own settings winning
+    let color = &index.textures[0];
+    assert_eq!((color.width, color.height, color.mipmaps, color.format, color.flags), (32, 32, 6, WtxFormat::DXT5, 0x04));
+    let plain = header(&dir.join("build/plain.wtx"));
+    assert_eq!((plain.mipmaps, plain.format, plain.flags.bits()), (1, WtxFormat::DXT1, 0x04));
+    // descriptions keep their own settings instead of the defaults, apart from what the texture sets
+    let panel = &index.textures[2];
+    assert_eq!((panel.width, panel.mipmaps, panel.format, panel.flags), (1024, 1, WtxFormat::DXT5, 0x01));
+}
+
+#[test]
+fn builds_json_manifest() {
+    let dir = test_dir("pack_json");
+    std::fs::write(dir.join("pack.json"), r#"{
+        "index": "textures.json",
+        "defaults": { "format": "DXT1" },
+        "texture": [
+            { "name": "a", "image": "images/a.png" },
+            { "name": "inline/panel", "panel": { "type": "color_panel", "background": "blueprint", "width": 4, "height": 4, "cells": [] }, "mipmaps": false }
+        ]
+    }"#).unwrap();
+    let manifest = PackManifest::load(&dir.join("pack.json")).unwrap();
+    assert_eq!(manifest.index_path(&dir), dir.join("textures.json"));
+    let index = build_pack(&manifest, &dir).unwrap();
+
+    let written: PackIndex = serde_json::from_str(&std::fs::read_to_string(dir.join("textures.json")).unwrap()).unwrap();
+    assert_eq!(written, index);
+    assert_eq!(index.textures[0].format, WtxFormat::DXT1);
+    assert_eq!(index.textures[1].file, PathBuf::from("inline/panel.wtx"));
+    assert_eq!(header(&dir.join("inline/panel.wtx")).format, WtxFormat::DXT5);
+}
+
+#[test]
+fn broken_manifests_are_errors() {
+    let dir = test_dir("pack_invalid");
+    let invalid = |toml: &str| match PackManifest::from_toml(toml).and_then(|manifest| build_pack(&manifest, &dir)) {
+        Err(Error::InvalidManifest(_)) => {},
+        other => panic!("{:?} for {}", other, toml),
+    };
+    invalid("not = [toml");
+    invalid("[[texture]]\nimage = \"images/a.png\"");
+    invalid("[[texture]]\nname = \"no_source\"");
+    invalid("[[texture]]\nname = \"two\"\nimage = \"images/a.png\"\ndescription = \"a.json\"");
+    invalid("[[texture]]\nname = \"a\"\nimage = \"images/a.png\"\n[[texture]]\nname = \"a\"\nimage = \"images/a.png\"");
+    invalid("[[texture]]\nname = \"../outside\"\nimage = \"images/a.png\"");
+    invalid("[[texture]]\nname = \"\"\nimage = \"images/a.png\"");
+    assert!(matches!(PackManifest::from_json("{ \"texture\": 3 }"), Err(Error::InvalidManifest(_))));
+    // nothing gets built for a manifest that doesn't validate
+    assert!(!dir.join("a.wtx").exists() && !dir.join("index.json").exists());
+}
+
+#[test]
+fn broken_textures_name_the_texture() {
+    let dir = test_dir("pack_texture_errors");
+    std::fs::write(dir.join("bad.json"), r#"{ "type": "color_panel", "background": "white", "width": 4, "height": 5, "cells": [] }"#).unwrap();
+    let failing = |texture: &str| {
+        let manifest = PackManifest::from_toml(&format!("[[texture]]\nname = \"broken\"\n{}", texture)).unwrap();
+        match build_pack(&manifest, &dir) {
+            Err(Error::PackTexture { name, error }) if name == "broken" => *error,
+            other => panic!("{:?} for {}", other, texture),
+        }
+    };
+    assert!(matches!(failing("image = \"images/missing.png\""), Error::Io(_)));
+    assert!(matches!(failing("image = \"bad.json\""), Error::InvalidImage(_)));
+    assert!(matches!(failing("description = \"images/a.png\""), Error::InvalidDescription(_)));
+    assert!(matches!(failing("description = \"bad.json\""), Error::UnsupportedPanelSize { width: 4, height: 5 }));
+    assert!(!dir.join("index.json").exists());
+}
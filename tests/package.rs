@@ -0,0 +1,240 @@
+//! Reads small asset packages built here, with the same layout as the game's: stored and deflated files in a zip,
+//! some of them in directories. Also runs `encoder extract` on one.
+
+use image::{ImageBuffer, Rgba};
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::process::Command;
+use wtx_tools::package::{crc32, AssetPackage, Compression};
+use wtx_tools::{Error, WtxEncodeOptions};
+
+struct TestFile {
+    name: &'static str,
+    data: Vec<u8>,
+    deflate: bool,
+}
+
+fn le16(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn le32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn le64(out: &mut Vec<u8>, v: u64) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+/// writes a zip file. with `zip64`, sizes, offsets and counts go in the zip64 records instead, like in a package over 4GB
+fn build_package(files: &[TestFile], zip64: bool) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut directory = Vec::new();
+    for file in files {
+        let stored = match file.deflate {
+            true => miniz_oxide::deflate::compress_to_vec(&file.data, 6),
+            false => file.data.clone(),
+        };
+        let offset = out.len() as u32;
+        let (method, crc) = (if file.deflate { 8 } else { 0 }, crc32(&file.data));
+
+        le32(&mut out, 0x04034b50);
+        le16(&mut out, 20);
+        le16(&mut out, 0);
+        le16(&mut out, method);
+        le32(&mut out, 0); // time and date
+        le32(&mut out, crc);
+        le32(&mut out, stored.len() as u32);
+        le32(&mut out, file.data.len() as u32);
+        le16(&mut out, file.name.len() as u16);
+        le16(&mut out, 0);
+        out.extend_from_slice(file.name.as_bytes());
+        out.extend_from_slice(&stored);
+
+        let max = |v: u32| if zip64 { 0xffffffff } else { v };
+        le32(&mut directory, 0x02014b50);
+        le16(&mut directory, 45);
+        le16(&mut directory, 20);
+        le16(&mut directory, 0);
+        le16(&mut directory, method);
+        le32(&mut directory, 0);
+        le32(&mut directory, crc);
+        le32(&mut directory, max(stored.len() as u32));
+        le32(&mut directory, max(file.data.len() as u32));
+        le16(&mut directory, file.name.len() as u16);
+        le16(&mut directory, if zip64 { 28 } else { 0 });
+        le16(&mut directory, 0);
+        le16(&mut directory, 0);
+        le16(&mut directory, 0);
+        le32(&mut directory, 0);
+        le32(&mut directory, max(offset));
+        directory.extend_from_slice(file.name.as_bytes());
+        if zip64 {
+            le16(&mut directory, 0x0001);
+            le16(&mut directory, 24);
+            le64(&mut directory, file.data.len() as u64);
+            le64(&mut directory, stored.len() as u64);
+            le64(&mut directory, offset as u64);
+        }
+    }
+
+    let directory_offset = out.len();
+    out.extend_from_slice(&directory);
+    if zip64 {
+        let record_offset = out.len();
+        le32(&mut out, 0x06064b50);
+        le64(&mut out, 44);
+        le16(&mut out, 45);
+        le16(&mut out, 45);
+        le32(&mut out, 0);
+        le32(&mut out, 0);
+        le64(&mut out, files.len() as u64);
+        le64(&mut out, files.len() as u64);
+        le64(&mut out, directory.len() as u64);
+        le64(&mut out, directory_offset as u64);
+        le32(&mut out, 0x07064b50);
+        le32(&mut out, 0);
+        le64(&mut out, record_offset as u64);
+        le32(&mut out, 1);
+    }
+    let count = if zip64 { 0xffff } else { files.len() as u16 };
+    le32(&mut out, 0x06054b50);
+    le32(&mut out, 0);
+    le16(&mut out, count);
+    le16(&mut out, count);
+    le32(&mut out, directory.len() as u32);
+    le32(&mut out, if zip64 { 0xffffffff } else { directory_offset as u32 });
+    let comment = b"a comment, which the reader has to skip";
+    le16(&mut out, comment.len() as u16);
+    out.extend_from_slice(comment);
+    out
+}
+
+fn test_wtx() -> Vec<u8> {
+    let img = ImageBuffer::from_fn(64, 32, |x, y| Rgba([(x * 4) as u8, (y * 8) as u8, 128, 255]));
+    wtx_tools::encode_wtx(img, &WtxEncodeOptions { mipmaps: true, ..Default::default() }).unwrap()
+}
+
+fn test_files() -> Vec<TestFile> {
+    vec![
+        TestFile { name: "textures/", data: vec![], deflate: false },
+        TestFile { name: "textures/obj_panel.wtx", data: test_wtx(), deflate: true },
+        TestFile { name: "readme.txt", data: b"stored as is".to_vec(), deflate: false },
+        TestFile { name: "textures/empty.wtx", data: vec![], deflate: true },
+    ]
+}
+
+fn check_package(zip: Vec<u8>) {
+    let mut package = AssetPackage::new(Cursor::new(zip)).unwrap();
+    let names: Vec<&str> = package.entries().iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["textures/", "textures/obj_panel.wtx", "readme.txt", "textures/empty.wtx"]);
+
+    let wtx = package.find("textures/obj_panel.wtx").unwrap().clone();
+    assert_eq!(wtx.compression, Compression::Deflate);
+    assert!(wtx.is_wtx() && !wtx.is_dir());
+    assert!(wtx.compressed_size < wtx.size);
+    assert_eq!(package.read(&wtx).unwrap(), test_wtx());
+    let img = wtx_tools::decode_wtx(&package.read(&wtx).unwrap()).unwrap();
+    assert_eq!(img.dimensions(), (64, 32));
+
+    assert!(package.find("textures/").unwrap().is_dir());
+    assert_eq!(package.find("readme.txt").unwrap().compression, Compression::Stored);
+    assert_eq!(package.read_file("readme.txt").unwrap(), b"stored as is");
+    assert_eq!(package.read_file("textures/empty.wtx").unwrap(), b"");
+    assert!(matches!(package.read_file("missing.wtx"), Err(Error::EntryNotFound(name)) if name == "missing.wtx"));
+}
+
+#[test]
+fn reads_package() {
+    check_package(build_package(&test_files(), false));
+}
+
+#[test]
+fn reads_zip64_package() {
+    check_package(build_package(&test_files(), true));
+}
+
+#[test]
+fn damaged_packages_are_errors() {
+    assert!(matches!(AssetPackage::new(Cursor::new(b"not a zip file".to_vec())), Err(Error::InvalidPackage(_))));
+    assert!(matches!(AssetPackage::new(Cursor::new(Vec::new())), Err(Error::InvalidPackage(_))));
+
+    // a flipped byte in the stored file is caught by the CRC
+    let mut zip = build_package(&test_files(), false);
+    let at = zip.windows(12).position(|w| w == b"stored as is").unwrap();
+    zip[at] ^= 0xff;
+    let mut package = AssetPackage::new(Cursor::new(zip)).unwrap();
+    assert!(matches!(package.read_file("readme.txt"), Err(Error::InvalidPackage(_))));
+    assert!(package.read_file("textures/obj_panel.wtx").is_ok());
+
+    // cut off in the middle of the directory
+    let zip = build_package(&test_files(), false);
+    let directory = zip.windows(4).position(|w| w == 0x02014b50u32.to_le_bytes()).unwrap();
+    let mut truncated = zip[..directory + 10].to_vec();
+    truncated.extend_from_slice(&zip[zip.len() - 22 - 39..]);
+    assert!(AssetPackage::new(Cursor::new(truncated)).is_err());
+}
+
+#[test]
+fn entries_stay_inside_the_output_directory() {
+    let files = [
+        TestFile { name: "../outside.txt", data: b"no".to_vec(), deflate: false },
+        TestFile { name: "/absolute.txt", data: b"no".to_vec(), deflate: false },
+    ];
+    let mut package = AssetPackage::new(Cursor::new(build_package(&files, false))).unwrap();
+    let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("package_inside");
+    for entry in package.entries().to_vec() {
+        assert_eq!(entry.relative_path(), None);
+        assert!(matches!(package.extract_to(&entry, &out_dir), Err(Error::InvalidPackage(_))));
+    }
+}
+
+/// `test_wtx()` with one u16 header field changed
+fn wtx_with_field(offset: usize, value: u16) -> Vec<u8> {
+    let mut wtx = test_wtx();
+    wtx[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    wtx
+}
+
+#[test]
+fn extract_skips_damaged_textures() {
+    // a texture that can't be decoded is reported, and the rest are still extracted
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("package_damaged");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let files = [
+        TestFile { name: "no_mipmaps.wtx", data: wtx_with_field(18, 0), deflate: false },
+        TestFile { name: "fine.wtx", data: test_wtx(), deflate: false },
+    ];
+    std::fs::write(dir.join("data-pc.zip"), build_package(&files, false)).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_encoder"))
+        .arg("extract").arg(dir.join("data-pc.zip")).arg("--png").arg("-o").arg(&dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(dir.join("fine.png").exists() && !dir.join("no_mipmaps.png").exists());
+}
+
+#[test]
+fn encoder_extract() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("package_extract");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let package = dir.join("data-pc.zip");
+    std::fs::write(&package, build_package(&test_files(), false)).unwrap();
+
+    let list = Command::new(env!("CARGO_BIN_EXE_encoder")).arg("extract").arg(&package).arg("--list").output().unwrap();
+    assert!(list.status.success());
+    let list = String::from_utf8(list.stdout).unwrap();
+    assert!(list.contains("textures/obj_panel.wtx") && list.contains("readme.txt"), "{}", list);
+
+    let out_dir = dir.join("out");
+    let status = Command::new(env!("CARGO_BIN_EXE_encoder"))
+        .arg("extract").arg(&package).arg("*.wtx").arg("--png").arg("-o").arg(&out_dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(std::fs::read(out_dir.join("textures/obj_panel.wtx")).unwrap(), test_wtx());
+    assert_eq!(image::open(out_dir.join("textures/obj_panel.png")).unwrap().into_rgba8().dimensions(), (64, 32));
+    assert!(!out_dir.join("readme.txt").exists());
+}
@@ -0,0 +1,76 @@
+//! Checks the python module from the outside, with `cargo test --features python`.
+//!
+//! The cdylib is copied next to a script as `wtx_tools.so` and imported by python, which encodes and decodes
+//! a texture with it. If no python is found (`python3`, or whatever `PYTHON` is set to), the test is skipped.
+
+use image::{ImageBuffer, Rgba};
+use std::path::PathBuf;
+use std::process::Command;
+
+const SCRIPT: &str = r#"
+import sys
+import wtx_tools
+
+png = open(sys.argv[1], "rb").read()
+pixels = open(sys.argv[2], "rb").read()
+
+# raw pixels, with mipmaps. block compression of this gradient is off by up to 28
+wtx = wtx_tools.generate_wtx_from_image(pixels, 16, 8, mipmaps=True, format="DXT1", quality="slow")
+header = wtx_tools.read_header(wtx)
+assert (header["width"], header["height"], header["mipmaps"], header["format"]) == (16, 8, 5, "DXT1"), header
+width, height, rgba = wtx_tools.decode_wtx(wtx)
+assert (width, height, len(rgba)) == (16, 8, len(pixels))
+assert max(abs(a - b) for a, b in zip(rgba, pixels)) <= 32
+
+# an image file, without mipmaps by default like the encoder
+wtx = wtx_tools.image_file_to_wtx(png, flags=wtx_tools.FLAG_SRGB)
+header = wtx_tools.read_header(wtx)
+assert (header["mipmaps"], header["format"], header["flags"]) == (1, "DXT5", wtx_tools.FLAG_SRGB), header
+width, height, rgba = wtx_tools.decode_wtx(wtx)
+assert (width, height) == (16, 8)
+assert max(abs(a - b) for a, b in zip(rgba, pixels)) <= 32
+
+for bad in [lambda: wtx_tools.decode_wtx(b"not a wtx"), lambda: wtx_tools.image_file_to_wtx(png, format="DXT3")]:
+    try:
+        bad()
+    except ValueError:
+        pass
+    else:
+        raise AssertionError("no ValueError")
+print("ok")
+"#;
+
+#[test]
+fn encode_decode_round_trip() {
+    let python = std::env::var("PYTHON").unwrap_or_else(|_| "python3".to_string());
+    if Command::new(&python).arg("--version").output().is_err() {
+        eprintln!("skipping, no python found (tried `{}`)", python);
+        return;
+    }
+
+    let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("python");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    std::fs::create_dir_all(&out_dir).unwrap();
+
+    // `cargo test` builds the cdylib next to the test binary, python finds it by the module's name
+    let lib_dir = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    let library = lib_dir.join(format!("{}wtx_tools{}", std::env::consts::DLL_PREFIX, std::env::consts::DLL_SUFFIX));
+    let module = out_dir.join(if cfg!(windows) { "wtx_tools.pyd" } else { "wtx_tools.so" });
+    std::fs::copy(&library, &module).unwrap_or_else(|e| panic!("copying {:?}: {}", library, e));
+
+    let img = ImageBuffer::from_fn(16, 8, |x, y| Rgba([(x * 16) as u8, (y * 32) as u8, 128, 255]));
+    img.save(out_dir.join("image.png")).unwrap();
+    std::fs::write(out_dir.join("pixels.rgba"), img.as_raw()).unwrap();
+    std::fs::write(out_dir.join("test.py"), SCRIPT).unwrap();
+
+    let output = Command::new(&python)
+        .args(["test.py", "image.png", "pixels.rgba"])
+        .current_dir(&out_dir)
+        .env("PYTHONPATH", &out_dir)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "python failed\nstdout:\n{}\nstderr:\n{}", stdout, stderr);
+    assert_eq!(stdout.trim(), "ok");
+}